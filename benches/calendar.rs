@@ -0,0 +1,99 @@
+use calendar::{Calendar, Occurrence, Schedule};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// 予定の数
+const SCHEDULES: u64 = 100_000;
+
+fn start() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2024, 1, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+}
+
+/// 2時間おきに1時間の予定が入ったカレンダー（最初の10件は毎週の繰り返し予定）
+fn calendar() -> Calendar {
+    let schedules = (0..SCHEDULES)
+        .map(|id| {
+            let start = start() + Duration::hours(2 * id as i64);
+            Schedule {
+                tz: Some(Tz::UTC),
+                recurrence: (id < 10).then(|| "FREQ=WEEKLY;COUNT=52".parse().unwrap()),
+                ..Schedule::new(id, format!("予定{}", id), start, start + Duration::hours(1))
+            }
+        })
+        .collect();
+    Calendar::new(schedules, SCHEDULES)
+}
+
+/// 索引を使わずにすべての予定を調べる（索引を入れる前のやり方）
+fn linear_conflicts<'a>(
+    calendar: &'a Calendar,
+    schedule: &Schedule,
+) -> Vec<(&'a Schedule, Occurrence<'a>)> {
+    calendar
+        .schedules()
+        .iter()
+        .filter_map(|existing| {
+            schedule
+                .overlap(existing)
+                .map(|occurrence| (existing, occurrence))
+        })
+        .collect()
+}
+
+fn linear_range(calendar: &Calendar, from: DateTime<Utc>, to: DateTime<Utc>) -> usize {
+    calendar
+        .schedules()
+        .iter()
+        .map(|schedule| schedule.occurrences_between(from, to).len())
+        .sum()
+}
+
+fn bench_conflicts(c: &mut Criterion) {
+    let calendar = calendar();
+    // 期間の中ほどにある予定と重なる予定
+    let at = start() + Duration::hours(SCHEDULES as i64);
+    let candidate = Schedule {
+        tz: Some(Tz::UTC),
+        ..Schedule::new(0, "追加する予定".to_string(), at, at + Duration::hours(3))
+    };
+    assert_eq!(
+        calendar.conflicts(&candidate),
+        linear_conflicts(&calendar, &candidate)
+    );
+
+    let mut group = c.benchmark_group("conflicts");
+    group.bench_function("indexed", |b| {
+        b.iter(|| calendar.conflicts(black_box(&candidate)))
+    });
+    group.bench_function("linear", |b| {
+        b.iter(|| linear_conflicts(&calendar, black_box(&candidate)))
+    });
+    group.finish();
+}
+
+fn bench_range(c: &mut Criterion) {
+    let calendar = calendar();
+    // 期間の中ほどの1週間
+    let from = (start() + Duration::hours(SCHEDULES as i64)).and_utc();
+    let to = from + Duration::days(7);
+    assert_eq!(
+        calendar.iter_range(from, to).count(),
+        linear_range(&calendar, from, to)
+    );
+
+    let mut group = c.benchmark_group("iter_range");
+    group.bench_function("indexed", |b| {
+        b.iter(|| calendar.iter_range(black_box(from), black_box(to)).count())
+    });
+    group.bench_function("linear", |b| {
+        b.iter(|| linear_range(&calendar, black_box(from), black_box(to)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_conflicts, bench_range);
+criterion_main!(benches);
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// 参加者の出欠の返事 (PARTSTAT)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rsvp {
+    /// 未回答
+    #[default]
+    NeedsAction,
+    /// 出席
+    Accepted,
+    /// 欠席
+    Declined,
+    /// 未定
+    Tentative,
+}
+
+impl Rsvp {
+    /// 指定や保存に使う名前（`accepted` など）
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Rsvp::NeedsAction => "needs-action",
+            Rsvp::Accepted => "accepted",
+            Rsvp::Declined => "declined",
+            Rsvp::Tentative => "tentative",
+        }
+    }
+}
+
+impl fmt::Display for Rsvp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Rsvp::NeedsAction => "未回答",
+            Rsvp::Accepted => "出席",
+            Rsvp::Declined => "欠席",
+            Rsvp::Tentative => "未定",
+        };
+        f.write_str(label)
+    }
+}
+
+impl FromStr for Rsvp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            Rsvp::NeedsAction,
+            Rsvp::Accepted,
+            Rsvp::Declined,
+            Rsvp::Tentative,
+        ]
+        .into_iter()
+        .find(|rsvp| rsvp.as_str().eq_ignore_ascii_case(s.trim()))
+        .ok_or_else(|| {
+            format!(
+                "返事「{}」は needs-action, accepted, declined, tentative のいずれかで指定してください",
+                s
+            )
+        })
+    }
+}
+
+/// 予定の参加者
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attendee {
+    /// 表示名（空ならメールアドレスで表示する）
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    pub email: String,
+    #[serde(default)]
+    pub status: Rsvp,
+}
+
+impl Attendee {
+    /// メールアドレスが `email` の人か（メールアドレスの大文字小文字は区別しない）
+    pub fn is(&self, email: &str) -> bool {
+        self.email.eq_ignore_ascii_case(email)
+    }
+}
+
+impl fmt::Display for Attendee {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.name.is_empty() {
+            write!(f, "<{}>", self.email)
+        } else {
+            write!(f, "{} <{}>", self.name, self.email)
+        }
+    }
+}
+
+/// `名前 <メールアドレス>` または `メールアドレス` の後ろに、任意で `:返事` を付けたもの
+///
+/// 例: `山田太郎 <taro@example.com>:accepted`
+impl FromStr for Attendee {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (address, status) = match s.rsplit_once(':') {
+            Some((address, status)) if !status.contains(['<', '>', '@']) => {
+                (address.trim(), status.parse()?)
+            }
+            _ => (s, Rsvp::default()),
+        };
+        let (name, email) = match address.strip_suffix('>').and_then(|a| a.rsplit_once('<')) {
+            Some((name, email)) => (name.trim(), email.trim()),
+            None => ("", address),
+        };
+        if email.is_empty()
+            || !email.contains('@')
+            || email.contains(|c: char| c.is_whitespace() || matches!(c, '<' | '>'))
+        {
+            return Err(format!(
+                "参加者「{}」は「名前 <メールアドレス>」の形式で指定してください",
+                s
+            ));
+        }
+        Ok(Attendee {
+            name: name.to_string(),
+            email: email.to_string(),
+            status,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn attendee(name: &str, email: &str, status: Rsvp) -> Attendee {
+        Attendee {
+            name: name.to_string(),
+            email: email.to_string(),
+            status,
+        }
+    }
+
+    #[rstest]
+    #[case(
+        "山田太郎 <taro@example.com>",
+        Ok(attendee("山田太郎", "taro@example.com", Rsvp::NeedsAction))
+    )]
+    #[case(
+        "山田太郎 <taro@example.com>:Accepted",
+        Ok(attendee("山田太郎", "taro@example.com", Rsvp::Accepted))
+    )]
+    #[case(
+        "taro@example.com:declined",
+        Ok(attendee("", "taro@example.com", Rsvp::Declined))
+    )]
+    #[case(
+        "<taro@example.com>",
+        Ok(attendee("", "taro@example.com", Rsvp::NeedsAction))
+    )]
+    #[case("山田太郎", Err(()))]
+    #[case("山田太郎 <taro@example.com>:maybe", Err(()))]
+    #[case("山田太郎 <taro example.com>", Err(()))]
+    fn test_parse_attendee(#[case] input: &str, #[case] expected: Result<Attendee, ()>) {
+        assert_eq!(input.parse::<Attendee>().map_err(|_| ()), expected);
+    }
+
+    #[test]
+    fn test_attendee_defaults() {
+        // 返事のない古いデータは未回答として読む
+        let parsed: Attendee = serde_json::from_str(r#"{"email":"taro@example.com"}"#).unwrap();
+        assert_eq!(parsed, attendee("", "taro@example.com", Rsvp::NeedsAction));
+        assert_eq!(
+            serde_json::to_string(&attendee("山田", "taro@example.com", Rsvp::Tentative)).unwrap(),
+            r#"{"name":"山田","email":"taro@example.com","status":"tentative"}"#
+        );
+    }
+}
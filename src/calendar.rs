@@ -0,0 +1,1394 @@
+use crate::{
+    attendee::Attendee,
+    error::CalendarError,
+    index::Index,
+    schedule::{Availability, Occurrence, Override, Schedule, ScheduleId},
+    tag::TagQuery,
+    zone,
+};
+use chrono::{DateTime, Days, NaiveDateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashSet},
+};
+
+/// 重複時に空いている時間を探すとき、前後それぞれにずらしてみる回数の上限
+const SUGGEST_ATTEMPTS: usize = 1000;
+
+/// 予定の集まり（予定ファイルの内容）
+///
+/// 期間で探す操作（重複判定や一覧）は索引を使うので、予定の数によらず速い。
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(from = "Saved")]
+pub struct Calendar {
+    schedules: Vec<Schedule>,
+    /// 次に割り当てるID（削除されたIDも再利用しない）
+    next_id: ScheduleId,
+    #[serde(skip)]
+    index: Index,
+}
+
+/// 予定ファイルに保存されている内容
+#[derive(Deserialize)]
+struct Saved {
+    schedules: Vec<Schedule>,
+    #[serde(default)]
+    next_id: ScheduleId,
+}
+
+impl From<Saved> for Calendar {
+    fn from(saved: Saved) -> Self {
+        Calendar::new(saved.schedules, saved.next_id)
+    }
+}
+
+/// 索引は予定から決まるので、予定と払い出し状況だけを比べる
+impl PartialEq for Calendar {
+    fn eq(&self, other: &Self) -> bool {
+        self.schedules == other.schedules && self.next_id == other.next_id
+    }
+}
+
+impl Eq for Calendar {}
+
+impl Calendar {
+    /// 保存されていた予定と、次に割り当てるIDから作る
+    pub fn new(mut schedules: Vec<Schedule>, next_id: ScheduleId) -> Self {
+        for schedule in schedules
+            .iter_mut()
+            .filter(|schedule| schedule.uid.is_empty())
+        {
+            schedule.uid = schedule.legacy_uid();
+        }
+        // next_id を持たない古いファイルや手編集されたファイルでも既存IDと衝突させない
+        let next_id = schedules
+            .iter()
+            .map(|schedule| schedule.id + 1)
+            .max()
+            .unwrap_or(0)
+            .max(next_id);
+        let index = Index::build(&schedules);
+        Calendar {
+            schedules,
+            next_id,
+            index,
+        }
+    }
+
+    /// 新しい予定のIDを払い出す
+    fn allocate_id(&mut self) -> ScheduleId {
+        let id = self.next_id;
+        self.next_id = id + 1;
+        id
+    }
+
+    /// タイムゾーンを持たない古い予定に `tz` を設定する
+    pub fn fill_default_tz(&mut self, tz: Tz) {
+        let mut filled = false;
+        for schedule in self
+            .schedules
+            .iter_mut()
+            .filter(|schedule| schedule.tz.is_none())
+        {
+            schedule.tz = Some(tz);
+            filled = true;
+        }
+        // 絶対時刻が変わるので索引を作り直す
+        if filled {
+            self.index = Index::build(&self.schedules);
+        }
+    }
+
+    /// 保存されている予定（追加した順）
+    pub fn schedules(&self) -> &[Schedule] {
+        &self.schedules
+    }
+
+    /// 次に割り当てるID
+    pub fn next_id(&self) -> ScheduleId {
+        self.next_id
+    }
+
+    /// 指定IDの予定
+    pub fn find(&self, id: ScheduleId) -> Option<&Schedule> {
+        self.index
+            .position(id)
+            .map(|position| &self.schedules[position])
+    }
+
+    /// `[from, to)` と重なる発生がありうる予定を追加した順に返す
+    pub fn schedules_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<&Schedule> {
+        self.index
+            .query(from, to)
+            .into_iter()
+            .map(|position| &self.schedules[position])
+            .collect()
+    }
+
+    /// 予定を追加し、払い出したIDを返す
+    ///
+    /// `schedule.id` は無視する。正しくない予定や既存の予定と重なる予定は追加しない。
+    pub fn add(&mut self, mut schedule: Schedule) -> Result<ScheduleId, CalendarError> {
+        let problems = schedule.problems();
+        if !problems.is_empty() {
+            return Err(CalendarError::Invalid(problems));
+        }
+
+        // 予定の重複判定
+        if let Some((existing, _)) = self.conflicts(&schedule).first() {
+            return Err(CalendarError::Conflict(existing.id));
+        }
+
+        // 予定の追加（IDは重複がないと分かってから払い出す）
+        schedule.id = self.allocate_id();
+        let id = schedule.id;
+        self.insert(schedule);
+        Ok(id)
+    }
+
+    /// IDを払い出し済みの予定を、正しさや重複を確かめずに追加する
+    pub(crate) fn insert(&mut self, schedule: Schedule) {
+        self.next_id = self.next_id.max(schedule.id + 1);
+        self.index.insert(self.schedules.len(), &schedule);
+        self.schedules.push(schedule);
+    }
+
+    /// 同じIDの予定を、正しさや重複を確かめずに置き換える
+    pub(crate) fn replace(&mut self, schedule: Schedule) -> Result<(), CalendarError> {
+        let position = self.position(schedule.id)?;
+        self.index.remove(position, &self.schedules[position]);
+        self.index.insert(position, &schedule);
+        self.schedules[position] = schedule;
+        Ok(())
+    }
+
+    /// 指定IDの予定を削除し、削除した予定を返す
+    pub fn remove(&mut self, id: ScheduleId) -> Result<Schedule, CalendarError> {
+        let position = self.position(id)?;
+        self.index.remove(position, &self.schedules[position]);
+        self.index.close_gap(position);
+        Ok(self.schedules.remove(position))
+    }
+
+    /// 指定IDの予定をまとめて削除し、削除した予定を返す
+    ///
+    /// 同じIDを重ねて指定しても1度だけ削除する。見つからないIDがあれば何も削除せず、
+    /// 見つからなかったIDのエラーをすべて返す。
+    pub fn remove_all(&mut self, ids: &[ScheduleId]) -> Result<Vec<Schedule>, Vec<CalendarError>> {
+        let mut seen = HashSet::new();
+        let ids: Vec<ScheduleId> = ids.iter().copied().filter(|id| seen.insert(*id)).collect();
+        let errors: Vec<CalendarError> = ids
+            .iter()
+            .filter_map(|&id| self.position(id).err())
+            .collect();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(ids.into_iter().map(|id| self.remove(id).unwrap()).collect())
+    }
+
+    /// 変更後の予定 `edited` に、変更前にはなかった問題があればエラーにする
+    ///
+    /// 読み込んだ時点で正しくない予定でも、直すための変更はできるようにする。
+    fn check_new_problems(&self, edited: &Schedule) -> Result<(), CalendarError> {
+        let before = self
+            .find(edited.id)
+            .map(Schedule::problems)
+            .unwrap_or_default();
+        let problems: Vec<String> = edited
+            .problems()
+            .into_iter()
+            .filter(|problem| !before.contains(problem))
+            .collect();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(CalendarError::Invalid(problems))
+        }
+    }
+
+    /// 指定IDの予定の位置
+    fn position(&self, id: ScheduleId) -> Result<usize, CalendarError> {
+        self.index.position(id).ok_or(CalendarError::NotFound(id))
+    }
+
+    /// 正しくない予定とその問題点をすべて返す
+    pub fn validate(&self) -> Vec<(ScheduleId, String)> {
+        let mut problems = Vec::new();
+        let mut seen = HashSet::new();
+        for schedule in &self.schedules {
+            for problem in schedule.problems() {
+                problems.push((schedule.id, problem));
+            }
+            if !seen.insert(schedule.id) {
+                problems.push((schedule.id, "IDが他の予定と重複しています".to_string()));
+            }
+        }
+        problems
+    }
+
+    /// `schedule` と時間が重なる予定を、重なる最初の発生と組にして追加した順に返す
+    pub fn conflicts(&self, schedule: &Schedule) -> Vec<(&Schedule, Occurrence<'_>)> {
+        let to = schedule.last_end().unwrap_or(DateTime::<Utc>::MAX_UTC);
+        self.schedules_between(schedule.first_start(), to)
+            .into_iter()
+            .filter_map(|existing| {
+                schedule
+                    .overlap(existing)
+                    .map(|occurrence| (existing, occurrence))
+            })
+            .collect()
+    }
+
+    /// `[from, to)` と重なるすべての予定の発生を開始日時の昇順で返す
+    pub fn iter_range(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> impl Iterator<Item = (&Schedule, Occurrence<'_>)> {
+        let mut occurrences: Vec<(&Schedule, Occurrence)> = self
+            .schedules_between(from, to)
+            .into_iter()
+            .flat_map(|schedule| {
+                schedule
+                    .occurrences_between(from, to)
+                    .into_iter()
+                    .map(move |occurrence| (schedule, occurrence))
+            })
+            .collect();
+        occurrences.sort_by_key(|(schedule, occurrence)| (occurrence.start, schedule.id));
+        occurrences.into_iter()
+    }
+
+    /// `candidate` と同じ長さで、重複しない最も近い前後の時間にずらした予定を近い順（同じなら後ろが先）に返す
+    ///
+    /// 最初の発生と重なる予定をよけるように前後へずらしていく（終日の予定は日単位）。繰り返し予定で、
+    /// 最初の発生より後の発生だけが重なる場合はずらし方を決められないので候補に含めない。
+    pub fn suggest_slots(&self, candidate: &Schedule) -> Vec<Schedule> {
+        let tz = candidate.tz();
+        let length = candidate.end - candidate.start;
+        let shifted = |start: NaiveDateTime| Schedule {
+            start,
+            end: start + length,
+            ..candidate.clone()
+        };
+
+        let mut suggestions = Vec::new();
+        for later in [true, false] {
+            let mut start = candidate.start;
+            for _ in 0..SUGGEST_ATTEMPTS {
+                let schedule = shifted(start);
+                let conflicts = self.conflicts(&schedule);
+                if conflicts.is_empty() {
+                    suggestions.push(schedule);
+                    break;
+                }
+                // 最初の発生と重なる発生をよけた位置へずらす
+                let from = zone::resolve_local(tz, schedule.start).with_timezone(&Utc);
+                let to = zone::resolve_local(tz, schedule.end).with_timezone(&Utc);
+                let overlapping = conflicts
+                    .iter()
+                    .flat_map(|(conflict, _)| conflict.occurrences_between(from, to));
+                let next = if later {
+                    overlapping
+                        .map(|occurrence| occurrence.end.with_timezone(&tz).naive_local())
+                        .max()
+                } else {
+                    overlapping
+                        .map(|occurrence| {
+                            occurrence.start.with_timezone(&tz).naive_local() - length
+                        })
+                        .min()
+                };
+                match next {
+                    Some(next) if candidate.is_all_day() => start = align_to_day(next, later),
+                    Some(next) => start = next,
+                    None => break,
+                }
+            }
+        }
+        suggestions.sort_by_key(|schedule| (schedule.start - candidate.start).abs());
+        suggestions
+    }
+
+    /// 条件に合う予定の発生を予定の並び順で返す
+    ///
+    /// 期間の指定とは一部でも重なれば合うものとする。終わりが指定されていなければ、
+    /// 繰り返し予定は `until` までの発生を展開する。
+    pub fn filter(
+        &self,
+        filter: &Filter,
+        until: DateTime<Utc>,
+    ) -> Vec<(&Schedule, Occurrence<'_>)> {
+        let from = filter.from.unwrap_or(DateTime::<Utc>::MIN_UTC);
+        let contains = filter.contains.as_ref().map(|text| text.to_lowercase());
+        self.schedules_between(from, filter.to.unwrap_or(DateTime::<Utc>::MAX_UTC))
+            .into_iter()
+            .filter(|schedule| filter.ids.is_empty() || filter.ids.contains(&schedule.id))
+            .filter(|schedule| {
+                filter
+                    .tags
+                    .iter()
+                    .all(|query| query.matches(&schedule.tags))
+            })
+            .flat_map(|schedule| {
+                let to = match (filter.to, &schedule.recurrence) {
+                    (Some(to), _) => to,
+                    (None, None) => DateTime::<Utc>::MAX_UTC,
+                    (None, Some(_)) => until,
+                };
+                schedule
+                    .occurrences_between(from, to)
+                    .into_iter()
+                    .map(move |occurrence| (schedule, occurrence))
+            })
+            .filter(|(_, occurrence)| match &contains {
+                Some(text) => occurrence.subject.to_lowercase().contains(text),
+                None => true,
+            })
+            .collect()
+    }
+
+    /// 指定IDの予定のタイトル・日時・タイムゾーンを変更する（None の項目は変えない）
+    ///
+    /// 繰り返し予定の開始日時を変えると、取り消した回・変更した回も同じだけずらす。
+    pub fn edit(
+        &mut self,
+        id: ScheduleId,
+        subject: Option<String>,
+        start: Option<NaiveDateTime>,
+        end: Option<NaiveDateTime>,
+        tz: Option<Tz>,
+    ) -> Result<(), CalendarError> {
+        // 変更後の予定の作成
+        let mut edited = self.schedules[self.position(id)?].clone();
+        if let Some(subject) = subject {
+            edited.subject = subject;
+        }
+        if let Some(start) = start {
+            // ずらせない回はそのまま残し、下の検証で繰り返しにない回として弾く
+            let delta = start - edited.start;
+            let shift = |at: NaiveDateTime| at.checked_add_signed(delta).unwrap_or(at);
+            for exdate in &mut edited.exdates {
+                *exdate = shift(*exdate);
+            }
+            for o in &mut edited.overrides {
+                o.recurrence_id = shift(o.recurrence_id);
+                o.start = shift(o.start);
+                o.end = shift(o.end);
+            }
+            edited.start = start;
+        }
+        edited.end = end.unwrap_or(edited.end);
+        edited.tz = tz.or(edited.tz);
+        self.check_new_problems(&edited)?;
+
+        // 自分以外の予定との重複判定
+        if let Some((other, _)) = self
+            .conflicts(&edited)
+            .into_iter()
+            .find(|(schedule, _)| schedule.id != id)
+        {
+            return Err(CalendarError::Conflict(other.id));
+        }
+
+        // 予定の更新
+        self.replace(edited)
+    }
+
+    /// 予定の場所・説明・参加者を変更する（日時は変わらないので重複判定はしない）
+    ///
+    /// 場所・説明は空文字列を指定すると消す。`attendees` は [`Schedule::add_attendee`] で加え、
+    /// `removed` はメールアドレスで参加者を外す。
+    pub fn edit_details(
+        &mut self,
+        id: ScheduleId,
+        location: Option<String>,
+        description: Option<String>,
+        attendees: Vec<Attendee>,
+        removed: &[String],
+    ) -> Result<(), CalendarError> {
+        let mut edited = self.schedules[self.position(id)?].clone();
+        if let Some(location) = location {
+            edited.location = Some(location).filter(|location| !location.is_empty());
+        }
+        if let Some(description) = description {
+            edited.description = Some(description).filter(|description| !description.is_empty());
+        }
+        edited
+            .attendees
+            .retain(|attendee| !removed.iter().any(|email| attendee.is(email)));
+        for attendee in attendees {
+            edited.add_attendee(attendee);
+        }
+        self.replace(edited)
+    }
+
+    /// 終日の予定がその間の時間を占めるかどうかを変える
+    pub fn set_availability(
+        &mut self,
+        id: ScheduleId,
+        availability: Availability,
+    ) -> Result<(), CalendarError> {
+        let mut edited = self.schedules[self.position(id)?].clone();
+        if !edited.is_all_day() {
+            return Err(CalendarError::Invalid(vec![
+                "終日の予定ではありません".to_string()
+            ]));
+        }
+        edited.all_day = Some(availability);
+
+        // 時間を占めるようにするなら、自分以外の予定と重ならないこと
+        if let Some((other, _)) = self
+            .conflicts(&edited)
+            .into_iter()
+            .find(|(schedule, _)| schedule.id != id)
+        {
+            return Err(CalendarError::Conflict(other.id));
+        }
+        self.replace(edited)
+    }
+
+    /// 予定にタグを付ける（付いているタグはそのまま）
+    pub fn tag(&mut self, id: ScheduleId, tags: &[String]) -> Result<(), CalendarError> {
+        let mut schedule = self.schedules[self.position(id)?].clone();
+        for tag in tags {
+            if !schedule.tags.contains(tag) {
+                schedule.tags.push(tag.clone());
+            }
+        }
+        self.replace(schedule)
+    }
+
+    /// 予定からタグを外す（付いていないタグは無視する）
+    pub fn untag(&mut self, id: ScheduleId, tags: &[String]) -> Result<(), CalendarError> {
+        let mut schedule = self.schedules[self.position(id)?].clone();
+        schedule.tags.retain(|tag| !tags.contains(tag));
+        self.replace(schedule)
+    }
+
+    /// 使われているタグと、そのタグが付いた予定の数を多い順（同じなら名前順）に返す
+    pub fn tags(&self) -> Vec<(&str, usize)> {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for tag in self.schedules.iter().flat_map(|schedule| &schedule.tags) {
+            *counts.entry(tag).or_default() += 1;
+        }
+        let mut tags: Vec<(&str, usize)> = counts.into_iter().collect();
+        tags.sort_by_key(|&(_, count)| Reverse(count));
+        tags
+    }
+
+    /// 予定をまとめて取り込む（取り込めないものは理由ごとに報告する）
+    pub fn import(&mut self, schedules: Vec<Schedule>) -> ImportReport {
+        let mut report = ImportReport::default();
+        let mut uids: HashSet<String> = self
+            .schedules
+            .iter()
+            .map(|schedule| schedule.uid.clone())
+            .collect();
+        for mut schedule in schedules {
+            // UID が同じものは取り込み済みとみなす
+            if uids.contains(&schedule.uid) {
+                report.duplicates.push(schedule);
+                continue;
+            }
+
+            let problems = schedule.problems();
+            if !problems.is_empty() {
+                report.invalid.push((schedule, problems));
+                continue;
+            }
+
+            // 予定の重複判定
+            if let Some((existing, _)) = self.conflicts(&schedule).first() {
+                let id = existing.id;
+                report.conflicts.push((schedule, id));
+                continue;
+            }
+
+            schedule.id = self.allocate_id();
+            report.imported.push(schedule.id);
+            uids.insert(schedule.uid.clone());
+            self.insert(schedule);
+        }
+        report
+    }
+
+    /// 繰り返し予定の `occurrence` に始まる回を取り消す
+    pub fn exclude(
+        &mut self,
+        id: ScheduleId,
+        occurrence: NaiveDateTime,
+    ) -> Result<(), CalendarError> {
+        let mut schedule = self.schedules[self.position(id)?].clone();
+        if !schedule.has_occurrence_at(occurrence) {
+            return Err(CalendarError::OccurrenceNotFound(id, occurrence));
+        }
+
+        // 変更済みの回を取り消す場合は変更内容も破棄する
+        schedule.overrides.retain(|o| o.recurrence_id != occurrence);
+        schedule.exdates.push(occurrence);
+        schedule.exdates.sort();
+        self.replace(schedule)
+    }
+
+    /// 繰り返し予定の `occurrence` に始まる回だけを変更する
+    pub fn override_occurrence(
+        &mut self,
+        id: ScheduleId,
+        occurrence: NaiveDateTime,
+        subject: Option<String>,
+        start: Option<NaiveDateTime>,
+        end: Option<NaiveDateTime>,
+    ) -> Result<(), CalendarError> {
+        let mut edited = self.schedules[self.position(id)?].clone();
+        if !edited.has_occurrence_at(occurrence) {
+            return Err(CalendarError::OccurrenceNotFound(id, occurrence));
+        }
+
+        // 既に変更済みの回なら、その内容に重ねて変更する
+        let current = match edited
+            .overrides
+            .iter()
+            .position(|o| o.recurrence_id == occurrence)
+        {
+            Some(position) => edited.overrides.remove(position),
+            None => Override {
+                recurrence_id: occurrence,
+                subject: None,
+                start: occurrence,
+                end: occurrence + (edited.end - edited.start),
+            },
+        };
+        edited.overrides.push(Override {
+            recurrence_id: occurrence,
+            subject: subject.or(current.subject),
+            start: start.unwrap_or(current.start),
+            end: end.unwrap_or(current.end),
+        });
+        edited.overrides.sort_by_key(|o| o.recurrence_id);
+        self.check_new_problems(&edited)?;
+
+        // 自分以外の予定との重複判定
+        if let Some((other, _)) = self
+            .conflicts(&edited)
+            .into_iter()
+            .find(|(schedule, _)| schedule.id != id)
+        {
+            return Err(CalendarError::Conflict(other.id));
+        }
+
+        self.replace(edited)
+    }
+}
+
+/// `at` を日の区切り（0 時）にそろえる（`later` なら後ろへ、そうでなければ前へ）
+fn align_to_day(at: NaiveDateTime, later: bool) -> NaiveDateTime {
+    let midnight = at.date().and_time(NaiveTime::MIN);
+    if later && midnight < at {
+        midnight + Days::new(1)
+    } else {
+        midnight
+    }
+}
+
+/// 予定の発生の絞り込み条件（指定のない条件では絞り込まない）
+#[derive(Debug, Default)]
+pub struct Filter {
+    /// この日時より後に終わる発生だけを残す
+    pub from: Option<DateTime<Utc>>,
+    /// この日時より前に始まる発生だけを残す
+    pub to: Option<DateTime<Utc>>,
+    /// タイトルにこの文字列を含む発生だけを残す
+    pub contains: Option<String>,
+    /// 空でなければ、これらのIDの予定だけを残す
+    pub ids: Vec<ScheduleId>,
+    /// タグがこれらの条件すべてに合う予定だけを残す
+    pub tags: Vec<TagQuery>,
+}
+
+/// 予定の取り込み結果
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// 取り込んだ予定のID
+    pub imported: Vec<ScheduleId>,
+    /// 同じ UID の予定が既にあったもの
+    pub duplicates: Vec<Schedule>,
+    /// 既存の予定と重複したもの（重複相手のIDと組）
+    pub conflicts: Vec<(Schedule, ScheduleId)>,
+    /// 予定として正しくないもの（問題点と組）
+    pub invalid: Vec<(Schedule, Vec<String>)>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{recurrence::Recurrence, test_util::native_date_time};
+    use rstest::rstest;
+
+    fn utc_date_time(
+        year: i32,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: u32,
+    ) -> DateTime<Utc> {
+        native_date_time(year, month, day, hour, minute, second).and_utc()
+    }
+
+    fn add_schedule(
+        calendar: &mut Calendar,
+        subject: String,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+        recurrence: Option<Recurrence>,
+        tz: Tz,
+    ) -> Result<ScheduleId, CalendarError> {
+        calendar.add(Schedule {
+            tz: Some(tz),
+            recurrence,
+            ..Schedule::new(0, subject, start, end)
+        })
+    }
+
+    #[test]
+    fn test_add_schedule() {
+        let mut calendar = Calendar::new(
+            vec![Schedule::new(
+                0,
+                "テスト予定".to_string(),
+                native_date_time(2024, 11, 19, 11, 22, 33),
+                native_date_time(2024, 11, 19, 22, 33, 44),
+            )],
+            1,
+        );
+        let existing = calendar.schedules[0].clone();
+        let id = add_schedule(
+            &mut calendar,
+            "テスト予定2".to_string(),
+            native_date_time(2023, 12, 8, 9, 0, 0),
+            native_date_time(2023, 12, 8, 10, 0, 0),
+            None,
+            Tz::UTC,
+        );
+        assert_eq!(id.unwrap(), 1);
+        let expected = Calendar::new(
+            vec![
+                existing,
+                Schedule {
+                    uid: calendar.schedules[1].uid.clone(),
+                    tz: Some(Tz::UTC),
+                    ..Schedule::new(
+                        1,
+                        "テスト予定2".to_string(),
+                        native_date_time(2023, 12, 8, 9, 0, 0),
+                        native_date_time(2023, 12, 8, 10, 0, 0),
+                    )
+                },
+            ],
+            2,
+        );
+        assert_eq!(calendar, expected);
+        assert_ne!(calendar.schedules[0].uid, calendar.schedules[1].uid);
+    }
+
+    #[rstest]
+    #[case(0, 2)]
+    #[case(5, 5)]
+    fn test_add_schedule_never_reuses_id(#[case] next_id: u64, #[case] expected_id: u64) {
+        // next_id を持たない古いファイル (next_id: 0) でも最大ID+1から払い出す
+        let mut calendar = Calendar::new(
+            vec![
+                Schedule::new(
+                    0,
+                    "テスト予定".to_string(),
+                    native_date_time(2024, 1, 1, 9, 0, 0),
+                    native_date_time(2024, 1, 1, 10, 0, 0),
+                ),
+                Schedule::new(
+                    1,
+                    "テスト予定2".to_string(),
+                    native_date_time(2024, 1, 1, 11, 0, 0),
+                    native_date_time(2024, 1, 1, 12, 0, 0),
+                ),
+            ],
+            next_id,
+        );
+        calendar.remove(0).unwrap();
+        let id = add_schedule(
+            &mut calendar,
+            "テスト予定3".to_string(),
+            native_date_time(2024, 1, 2, 9, 0, 0),
+            native_date_time(2024, 1, 2, 10, 0, 0),
+            None,
+            Tz::UTC,
+        );
+        assert_eq!(id.unwrap(), expected_id);
+        assert_eq!(calendar.schedules[1].id, expected_id);
+        assert_eq!(calendar.next_id, expected_id + 1);
+    }
+
+    #[test]
+    fn test_add_schedule_rejects_invalid() {
+        let mut calendar = Calendar::default();
+        assert!(matches!(
+            add_schedule(
+                &mut calendar,
+                "長すぎる予定".to_string(),
+                native_date_time(2024, 1, 1, 9, 0, 0),
+                native_date_time(2024, 3, 1, 9, 0, 0),
+                None,
+                Tz::UTC,
+            ),
+            Err(CalendarError::Invalid(_))
+        ));
+        assert!(calendar.schedules.is_empty());
+    }
+
+    #[test]
+    fn test_add_schedule_conflicts_with_occurrence() {
+        let mut calendar = Calendar::new(vec![], 0);
+        assert!(add_schedule(
+            &mut calendar,
+            "定例".to_string(),
+            native_date_time(2024, 1, 1, 9, 0, 0),
+            native_date_time(2024, 1, 1, 10, 0, 0),
+            Some("FREQ=WEEKLY;BYDAY=MO".parse().unwrap()),
+            Tz::UTC,
+        )
+        .is_ok());
+        assert!(matches!(
+            add_schedule(
+                &mut calendar,
+                "打ち合わせ".to_string(),
+                native_date_time(2025, 6, 2, 9, 30, 0),
+                native_date_time(2025, 6, 2, 10, 30, 0),
+                None,
+                Tz::UTC,
+            ),
+            Err(CalendarError::Conflict(0))
+        ));
+        assert_eq!(calendar.schedules.len(), 1);
+
+        // 重複の報告には最初の回ではなく重なっている回を返す
+        let candidate = Schedule::new(
+            1,
+            "打ち合わせ".to_string(),
+            native_date_time(2025, 6, 2, 9, 30, 0),
+            native_date_time(2025, 6, 2, 10, 30, 0),
+        );
+        let conflicts = calendar.conflicts(&candidate);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            conflicts[0].1.recurrence_id,
+            Some(native_date_time(2025, 6, 2, 9, 0, 0))
+        );
+    }
+
+    #[test]
+    fn test_remove_schedule() {
+        let mut calendar = Calendar::new(
+            vec![
+                Schedule::new(
+                    0,
+                    "テスト予定".to_string(),
+                    native_date_time(2024, 11, 19, 11, 22, 33),
+                    native_date_time(2024, 11, 19, 22, 33, 44),
+                ),
+                Schedule::new(
+                    1,
+                    "テスト予定2".to_string(),
+                    native_date_time(2023, 12, 8, 9, 0, 0),
+                    native_date_time(2023, 12, 8, 10, 0, 0),
+                ),
+            ],
+            2,
+        );
+        let removed = calendar.remove(0).unwrap();
+        assert_eq!(removed.subject, "テスト予定");
+        assert_eq!(calendar.schedules.len(), 1);
+        assert_eq!(calendar.schedules[0].id, 1);
+        assert_eq!(calendar.remove(0), Err(CalendarError::NotFound(0)));
+    }
+
+    #[rstest]
+    #[case(vec![0, 0], Ok(vec![0]), 1)]
+    #[case(vec![1, 0, 1], Ok(vec![1, 0]), 0)]
+    #[case(
+        vec![0, 5, 1, 6, 5],
+        Err(vec![CalendarError::NotFound(5), CalendarError::NotFound(6)]),
+        2
+    )]
+    fn test_remove_all_schedules(
+        #[case] ids: Vec<ScheduleId>,
+        #[case] expected: Result<Vec<ScheduleId>, Vec<CalendarError>>,
+        #[case] remaining: usize,
+    ) {
+        let mut calendar = Calendar::new(
+            vec![
+                Schedule::new(
+                    0,
+                    "テスト予定".to_string(),
+                    native_date_time(2024, 1, 1, 9, 0, 0),
+                    native_date_time(2024, 1, 1, 10, 0, 0),
+                ),
+                Schedule::new(
+                    1,
+                    "テスト予定2".to_string(),
+                    native_date_time(2024, 1, 2, 9, 0, 0),
+                    native_date_time(2024, 1, 2, 10, 0, 0),
+                ),
+            ],
+            2,
+        );
+        let result = calendar.remove_all(&ids).map(|removed| {
+            removed
+                .iter()
+                .map(|schedule| schedule.id)
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(result, expected);
+        // 失敗したときは何も削除しない
+        assert_eq!(calendar.schedules.len(), remaining);
+    }
+
+    #[rstest]
+    #[case(19, 0, 20, 0, Ok(()))]
+    #[case(9, 30, 10, 30, Ok(()))]
+    #[case(10, 30, 11, 30, Err(CalendarError::Conflict(1)))]
+    fn test_edit_schedule(
+        #[case] h0: u32,
+        #[case] m0: u32,
+        #[case] h1: u32,
+        #[case] m1: u32,
+        #[case] expected: Result<(), CalendarError>,
+    ) {
+        let mut calendar = Calendar::new(
+            vec![
+                Schedule::new(
+                    0,
+                    "テスト予定".to_string(),
+                    native_date_time(2024, 1, 1, 9, 0, 0),
+                    native_date_time(2024, 1, 1, 10, 0, 0),
+                ),
+                Schedule::new(
+                    1,
+                    "テスト予定2".to_string(),
+                    native_date_time(2024, 1, 1, 11, 0, 0),
+                    native_date_time(2024, 1, 1, 12, 0, 0),
+                ),
+            ],
+            2,
+        );
+        let result = calendar.edit(
+            0,
+            None,
+            Some(native_date_time(2024, 1, 1, h0, m0, 0)),
+            Some(native_date_time(2024, 1, 1, h1, m1, 0)),
+            None,
+        );
+        assert_eq!(result, expected);
+        if result.is_ok() {
+            assert_eq!(calendar.schedules[0].subject, "テスト予定");
+            assert_eq!(
+                calendar.schedules[0].start,
+                native_date_time(2024, 1, 1, h0, m0, 0)
+            );
+        }
+        assert_eq!(
+            calendar.edit(9, None, None, None, None),
+            Err(CalendarError::NotFound(9))
+        );
+    }
+
+    #[test]
+    fn test_exclude_and_override_occurrence() {
+        // 2024/1/1 から毎週月曜 9:00-10:00
+        let mut calendar = Calendar::new(
+            vec![
+                Schedule {
+                    recurrence: Some("FREQ=WEEKLY;BYDAY=MO".parse().unwrap()),
+                    ..Schedule::new(
+                        0,
+                        "定例".to_string(),
+                        native_date_time(2024, 1, 1, 9, 0, 0),
+                        native_date_time(2024, 1, 1, 10, 0, 0),
+                    )
+                },
+                Schedule::new(
+                    1,
+                    "来客".to_string(),
+                    native_date_time(2024, 1, 15, 15, 0, 0),
+                    native_date_time(2024, 1, 15, 16, 0, 0),
+                ),
+            ],
+            2,
+        );
+        assert_eq!(
+            calendar.exclude(0, native_date_time(2024, 1, 2, 9, 0, 0)),
+            Err(CalendarError::OccurrenceNotFound(
+                0,
+                native_date_time(2024, 1, 2, 9, 0, 0)
+            ))
+        );
+
+        // 取り消した回には別の予定を入れられる
+        calendar
+            .exclude(0, native_date_time(2024, 1, 8, 9, 0, 0))
+            .unwrap();
+        assert!(add_schedule(
+            &mut calendar,
+            "祝日出勤".to_string(),
+            native_date_time(2024, 1, 8, 9, 0, 0),
+            native_date_time(2024, 1, 8, 10, 0, 0),
+            None,
+            Tz::UTC,
+        )
+        .is_ok());
+
+        // 1回分だけ移動した先が他の予定と重なる場合は拒否する
+        assert_eq!(
+            calendar.override_occurrence(
+                0,
+                native_date_time(2024, 1, 15, 9, 0, 0),
+                None,
+                Some(native_date_time(2024, 1, 15, 15, 30, 0)),
+                Some(native_date_time(2024, 1, 15, 16, 30, 0)),
+            ),
+            Err(CalendarError::Conflict(1))
+        );
+        calendar
+            .override_occurrence(
+                0,
+                native_date_time(2024, 1, 15, 9, 0, 0),
+                Some("定例（午後）".to_string()),
+                Some(native_date_time(2024, 1, 15, 13, 0, 0)),
+                Some(native_date_time(2024, 1, 15, 14, 0, 0)),
+            )
+            .unwrap();
+
+        let occurrences: Vec<(NaiveDateTime, &str)> = calendar.schedules[0]
+            .occurrences_between(
+                native_date_time(2024, 1, 1, 0, 0, 0).and_utc(),
+                native_date_time(2024, 1, 23, 0, 0, 0).and_utc(),
+            )
+            .into_iter()
+            .map(|occurrence| (occurrence.start.naive_local(), occurrence.subject))
+            .collect();
+        assert_eq!(
+            occurrences,
+            vec![
+                (native_date_time(2024, 1, 1, 9, 0, 0), "定例"),
+                (native_date_time(2024, 1, 15, 13, 0, 0), "定例（午後）"),
+                (native_date_time(2024, 1, 22, 9, 0, 0), "定例"),
+            ]
+        );
+
+        // 元の時間帯が空いたので、そこに予定を入れられる
+        assert!(add_schedule(
+            &mut calendar,
+            "面談".to_string(),
+            native_date_time(2024, 1, 15, 9, 0, 0),
+            native_date_time(2024, 1, 15, 10, 0, 0),
+            None,
+            Tz::UTC,
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_edit_recurring_start_shifts_occurrences() {
+        // 2024/1/1 から毎週月曜 9:00-10:00 を4回。1/8 は取り消し、1/15 は 13:00 に移動
+        let mut calendar = Calendar::new(
+            vec![Schedule {
+                recurrence: Some("FREQ=WEEKLY;BYDAY=MO;COUNT=4".parse().unwrap()),
+                exdates: vec![native_date_time(2024, 1, 8, 9, 0, 0)],
+                overrides: vec![Override {
+                    recurrence_id: native_date_time(2024, 1, 15, 9, 0, 0),
+                    subject: None,
+                    start: native_date_time(2024, 1, 15, 13, 0, 0),
+                    end: native_date_time(2024, 1, 15, 14, 0, 0),
+                }],
+                ..Schedule::new(
+                    0,
+                    "定例".to_string(),
+                    native_date_time(2024, 1, 1, 9, 0, 0),
+                    native_date_time(2024, 1, 1, 10, 0, 0),
+                )
+            }],
+            1,
+        );
+
+        // 火曜にずらすと取り消した回が月曜の繰り返しから外れるので拒否する
+        let result = calendar.edit(
+            0,
+            None,
+            Some(native_date_time(2024, 1, 2, 9, 0, 0)),
+            Some(native_date_time(2024, 1, 2, 10, 0, 0)),
+            None,
+        );
+        assert!(matches!(result, Err(CalendarError::Invalid(_))));
+        let result = calendar.edit(
+            0,
+            None,
+            Some(native_date_time(2024, 1, 1, 10, 0, 0)),
+            Some(native_date_time(2024, 1, 1, 11, 0, 0)),
+            None,
+        );
+        assert_eq!(result, Ok(()));
+
+        let starts: Vec<NaiveDateTime> = calendar.schedules[0]
+            .occurrences_between(DateTime::<Utc>::MIN_UTC, DateTime::<Utc>::MAX_UTC)
+            .into_iter()
+            .map(|occurrence| occurrence.start.naive_local())
+            .collect();
+        assert_eq!(
+            starts,
+            vec![
+                native_date_time(2024, 1, 1, 10, 0, 0),
+                native_date_time(2024, 1, 15, 14, 0, 0),
+                native_date_time(2024, 1, 22, 10, 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_schedules() {
+        let mut calendar = Calendar::new(
+            vec![Schedule::new(
+                0,
+                "テスト予定".to_string(),
+                native_date_time(2024, 1, 1, 9, 0, 0),
+                native_date_time(2024, 1, 1, 10, 0, 0),
+            )],
+            1,
+        );
+        let imported = Schedule::new(
+            0,
+            "取り込む予定".to_string(),
+            native_date_time(2024, 1, 2, 9, 0, 0),
+            native_date_time(2024, 1, 2, 10, 0, 0),
+        );
+        let conflicting = Schedule::new(
+            0,
+            "重複する予定".to_string(),
+            native_date_time(2024, 1, 1, 9, 30, 0),
+            native_date_time(2024, 1, 1, 10, 30, 0),
+        );
+        let report = calendar.import(vec![imported.clone(), conflicting]);
+        assert_eq!(report.imported, vec![1]);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].1, 0);
+
+        // 同じ UID の予定を再度取り込んでも増えない
+        let report = calendar.import(vec![imported]);
+        assert!(report.imported.is_empty());
+        assert_eq!(report.duplicates.len(), 1);
+        assert_eq!(calendar.schedules.len(), 2);
+    }
+
+    #[rstest]
+    // 期間の指定なし
+    #[case(Filter::default(), vec![0, 1, 2])]
+    // 期間と一部だけ重なる予定も含む
+    #[case(Filter {
+        from: Some(utc_date_time(2024, 1, 1, 9, 30, 0)),
+        to: Some(utc_date_time(2024, 1, 2, 9, 30, 0)),
+        ..Default::default()
+    }, vec![0, 1])]
+    // 終了時刻ちょうどに始まる期間とは重ならない
+    #[case(Filter {
+        from: Some(utc_date_time(2024, 1, 1, 10, 0, 0)),
+        ..Default::default()
+    }, vec![1, 2])]
+    // 大文字小文字を区別しない
+    #[case(Filter { contains: Some("standup".to_string()), ..Default::default() }, vec![1])]
+    // 条件の組み合わせ
+    #[case(Filter {
+        from: Some(utc_date_time(2024, 1, 2, 0, 0, 0)),
+        ids: vec![0, 2],
+        ..Default::default()
+    }, vec![2])]
+    // タグの条件（複数指定はすべてを満たすもの）
+    #[case(Filter { tags: vec!["work".parse().unwrap()], ..Default::default() }, vec![1, 2])]
+    #[case(Filter {
+        tags: vec!["work".parse().unwrap(), "not review".parse().unwrap()],
+        ..Default::default()
+    }, vec![1])]
+    fn test_filter_occurrences(#[case] filter: Filter, #[case] expected: Vec<u64>) {
+        let calendar = Calendar::new(
+            vec![
+                Schedule::new(
+                    0,
+                    "テスト予定".to_string(),
+                    native_date_time(2024, 1, 1, 9, 0, 0),
+                    native_date_time(2024, 1, 1, 10, 0, 0),
+                ),
+                Schedule {
+                    tags: vec!["work".to_string()],
+                    ..Schedule::new(
+                        1,
+                        "Standup".to_string(),
+                        native_date_time(2024, 1, 2, 9, 0, 0),
+                        native_date_time(2024, 1, 2, 10, 0, 0),
+                    )
+                },
+                Schedule {
+                    tags: vec!["work".to_string(), "review".to_string()],
+                    ..Schedule::new(
+                        2,
+                        "レビュー".to_string(),
+                        native_date_time(2024, 1, 3, 9, 0, 0),
+                        native_date_time(2024, 1, 3, 10, 0, 0),
+                    )
+                },
+            ],
+            3,
+        );
+        let until = utc_date_time(2025, 1, 1, 0, 0, 0);
+        let ids: Vec<u64> = calendar
+            .filter(&filter, until)
+            .iter()
+            .map(|(schedule, _)| schedule.id)
+            .collect();
+        assert_eq!(ids, expected);
+    }
+
+    #[test]
+    fn test_edit_details() {
+        let mut calendar = Calendar::new(
+            vec![Schedule::new(
+                0,
+                "テスト予定".to_string(),
+                native_date_time(2024, 1, 1, 9, 0, 0),
+                native_date_time(2024, 1, 1, 10, 0, 0),
+            )],
+            1,
+        );
+        let taro: Attendee = "山田太郎 <taro@example.com>".parse().unwrap();
+        let hanako: Attendee = "hanako@example.com".parse().unwrap();
+        calendar
+            .edit_details(
+                0,
+                Some("会議室A".to_string()),
+                Some("1行目\n2行目".to_string()),
+                vec![taro.clone(), hanako.clone()],
+                &[],
+            )
+            .unwrap();
+        let schedule = calendar.find(0).unwrap();
+        assert_eq!(schedule.location.as_deref(), Some("会議室A"));
+        assert_eq!(schedule.description.as_deref(), Some("1行目\n2行目"));
+        assert_eq!(schedule.attendees, vec![taro.clone(), hanako]);
+
+        // 同じメールアドレスなら返事を更新し（名前がなければ元の名前を残す）、空文字列の場所は消す
+        let accepted: Attendee = "TARO@example.com:accepted".parse().unwrap();
+        calendar
+            .edit_details(
+                0,
+                Some(String::new()),
+                None,
+                vec![accepted.clone()],
+                &["Hanako@example.com".to_string()],
+            )
+            .unwrap();
+        let schedule = calendar.find(0).unwrap();
+        assert_eq!(schedule.location, None);
+        assert_eq!(schedule.description.as_deref(), Some("1行目\n2行目"));
+        assert_eq!(
+            schedule.attendees,
+            vec![Attendee {
+                name: "山田太郎".to_string(),
+                ..accepted
+            }]
+        );
+    }
+
+    #[test]
+    fn test_set_availability() {
+        let date = |day: u32| chrono::NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+        let mut calendar = Calendar::new(
+            vec![
+                Schedule::new_all_day(
+                    0,
+                    "締め切り".to_string(),
+                    date(1),
+                    date(1),
+                    Availability::Free,
+                ),
+                Schedule::new(
+                    1,
+                    "テスト予定".to_string(),
+                    native_date_time(2024, 1, 1, 9, 0, 0),
+                    native_date_time(2024, 1, 1, 10, 0, 0),
+                ),
+                Schedule::new_all_day(2, "休暇".to_string(), date(2), date(3), Availability::Free),
+            ],
+            3,
+        );
+
+        // 時間を占めるようにすると、同じ日の予定と重なる
+        assert_eq!(
+            calendar.set_availability(0, Availability::Busy),
+            Err(CalendarError::Conflict(1))
+        );
+        calendar.set_availability(2, Availability::Busy).unwrap();
+        assert_eq!(calendar.find(2).unwrap().all_day, Some(Availability::Busy));
+        assert_eq!(
+            calendar.add(Schedule::new(
+                0,
+                "来客".to_string(),
+                native_date_time(2024, 1, 3, 13, 0, 0),
+                native_date_time(2024, 1, 3, 14, 0, 0),
+            )),
+            Err(CalendarError::Conflict(2))
+        );
+
+        assert!(matches!(
+            calendar.set_availability(1, Availability::Free),
+            Err(CalendarError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_tag_schedules() {
+        let mut calendar = Calendar::new(
+            vec![
+                Schedule::new(
+                    0,
+                    "テスト予定".to_string(),
+                    native_date_time(2024, 1, 1, 9, 0, 0),
+                    native_date_time(2024, 1, 1, 10, 0, 0),
+                ),
+                Schedule::new(
+                    1,
+                    "レビュー".to_string(),
+                    native_date_time(2024, 1, 2, 9, 0, 0),
+                    native_date_time(2024, 1, 2, 10, 0, 0),
+                ),
+            ],
+            2,
+        );
+        let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+
+        // 付いているタグは重複させない
+        calendar.tag(0, &tags(&["work", "private"])).unwrap();
+        calendar.tag(0, &tags(&["work"])).unwrap();
+        calendar.tag(1, &tags(&["work"])).unwrap();
+        assert_eq!(calendar.find(0).unwrap().tags, tags(&["work", "private"]));
+        assert_eq!(calendar.tags(), vec![("work", 2), ("private", 1)]);
+
+        // 付いていないタグを外しても何も起きない
+        calendar.untag(0, &tags(&["private", "review"])).unwrap();
+        assert_eq!(calendar.find(0).unwrap().tags, tags(&["work"]));
+        assert_eq!(calendar.tags(), vec![("work", 2)]);
+
+        assert_eq!(
+            calendar.tag(5, &tags(&["work"])),
+            Err(CalendarError::NotFound(5))
+        );
+    }
+
+    #[test]
+    fn test_suggest_slots() {
+        let calendar = Calendar::new(
+            vec![
+                Schedule::new(
+                    0,
+                    "テスト予定".to_string(),
+                    native_date_time(2024, 1, 1, 9, 0, 0),
+                    native_date_time(2024, 1, 1, 10, 0, 0),
+                ),
+                Schedule::new(
+                    1,
+                    "テスト予定2".to_string(),
+                    native_date_time(2024, 1, 1, 10, 30, 0),
+                    native_date_time(2024, 1, 1, 11, 0, 0),
+                ),
+            ],
+            2,
+        );
+        let candidate = Schedule::new(
+            2,
+            "追加する予定".to_string(),
+            native_date_time(2024, 1, 1, 9, 30, 0),
+            native_date_time(2024, 1, 1, 10, 30, 0),
+        );
+        assert_eq!(
+            calendar
+                .conflicts(&candidate)
+                .iter()
+                .map(|(schedule, _)| schedule.id)
+                .collect::<Vec<_>>(),
+            vec![0]
+        );
+        // 後ろは 10:00-11:00 が ID 1 と重なるので 11:00 から、前は 8:00 から
+        let starts: Vec<NaiveDateTime> = calendar
+            .suggest_slots(&candidate)
+            .iter()
+            .map(|schedule| schedule.start)
+            .collect();
+        assert_eq!(
+            starts,
+            vec![
+                native_date_time(2024, 1, 1, 11, 0, 0),
+                native_date_time(2024, 1, 1, 8, 0, 0),
+            ]
+        );
+
+        // 終日の予定は日単位でずらす
+        let date = |day: u32| chrono::NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+        let candidate =
+            Schedule::new_all_day(2, "休暇".to_string(), date(1), date(1), Availability::Busy);
+        let starts: Vec<NaiveDateTime> = calendar
+            .suggest_slots(&candidate)
+            .iter()
+            .map(|schedule| schedule.start)
+            .collect();
+        assert_eq!(
+            starts,
+            vec![
+                native_date_time(2024, 1, 2, 0, 0, 0),
+                native_date_time(2023, 12, 31, 0, 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_calendar_validate() {
+        let calendar = Calendar::new(
+            vec![
+                Schedule::new(
+                    0,
+                    "テスト予定".to_string(),
+                    native_date_time(2024, 1, 1, 9, 0, 0),
+                    native_date_time(2024, 1, 1, 10, 0, 0),
+                ),
+                Schedule::new(
+                    1,
+                    "".to_string(),
+                    native_date_time(2024, 1, 2, 10, 0, 0),
+                    native_date_time(2024, 1, 2, 9, 0, 0),
+                ),
+                Schedule::new(
+                    0,
+                    "テスト予定2".to_string(),
+                    native_date_time(2024, 1, 3, 9, 0, 0),
+                    native_date_time(2024, 1, 3, 10, 0, 0),
+                ),
+            ],
+            2,
+        );
+        assert_eq!(
+            calendar.validate(),
+            vec![
+                (1, "タイトルが空です".to_string()),
+                (1, "終了日時が開始日時より後ではありません".to_string()),
+                (0, "IDが他の予定と重複しています".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+
+/// 現在時刻の取得元
+///
+/// 「今」に依存する処理はこれを受け取り、テストでは固定の時刻を差し込む。
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// システムの時計
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// 常に同じ時刻を返す時計
+#[cfg(test)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+#[cfg(test)]
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
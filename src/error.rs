@@ -0,0 +1,164 @@
+use crate::schedule::ScheduleId;
+use chrono::NaiveDateTime;
+use std::{fmt, io, path::PathBuf};
+
+/// 入出力の失敗（予定ファイルがない場合も含む）
+pub const EXIT_IO: i32 = 3;
+/// 予定ファイルを JSON として解釈できない
+pub const EXIT_PARSE: i32 = 4;
+/// 入力が正しくない
+pub const EXIT_VALIDATION: i32 = 5;
+/// 予定が重複している
+pub const EXIT_CONFLICT: i32 = 6;
+/// 指定した予定や回が見つからない
+pub const EXIT_NOT_FOUND: i32 = 7;
+
+/// このツールのエラー
+#[derive(Debug)]
+pub enum Error {
+    /// 予定ファイルがない
+    FileNotFound(PathBuf),
+    /// 作成しようとした予定ファイルがすでにある
+    FileExists(PathBuf),
+    /// ファイルの読み書きに失敗した
+    Io { path: PathBuf, source: io::Error },
+    /// 予定データベースを読み書きできない
+    Database {
+        path: PathBuf,
+        source: rusqlite::Error,
+    },
+    /// 予定ファイルを JSON として解釈できない（位置は `source.line()`、`source.column()`）
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// 入力が正しくない
+    Validation(String),
+    /// 予定の操作に失敗した
+    Calendar(CalendarError),
+}
+
+impl Error {
+    /// `path` の読み書きの失敗を表すエラーを作る
+    pub fn io(path: impl Into<PathBuf>) -> impl FnOnce(io::Error) -> Error {
+        let path = path.into();
+        move |source| Error::Io { path, source }
+    }
+
+    /// `path` の予定データベースの操作の失敗を表すエラーを作る
+    pub fn database(path: impl Into<PathBuf>) -> impl FnOnce(rusqlite::Error) -> Error {
+        let path = path.into();
+        move |source| Error::Database { path, source }
+    }
+
+    /// プロセスの終了コード
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::FileNotFound(_)
+            | Error::FileExists(_)
+            | Error::Io { .. }
+            | Error::Database { .. } => EXIT_IO,
+            Error::Parse { .. } => EXIT_PARSE,
+            Error::Validation(_) | Error::Calendar(CalendarError::Invalid(_)) => EXIT_VALIDATION,
+            Error::Calendar(CalendarError::Conflict(_)) => EXIT_CONFLICT,
+            Error::Calendar(CalendarError::NotFound(_))
+            | Error::Calendar(CalendarError::OccurrenceNotFound(..)) => EXIT_NOT_FOUND,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::FileNotFound(path) => write!(
+                f,
+                "予定ファイル {} がありません（`calendar init` で作成できます）",
+                path.display()
+            ),
+            Error::FileExists(path) => write!(
+                f,
+                "予定ファイル {} はすでにあります（空で上書きする場合は --force を指定してください）",
+                path.display()
+            ),
+            Error::Io { path, source } => write!(f, "{} を読み書きできません: {}", path.display(), source),
+            Error::Database { path, source } => {
+                write!(f, "データベース {} を読み書きできません: {}", path.display(), source)
+            }
+            // serde_json のメッセージには行と桁が含まれる（"... at line 3 column 5"）
+            Error::Parse { path, source } => {
+                write!(f, "{} を解釈できません: {}", path.display(), source)
+            }
+            Error::Validation(message) => write!(f, "{}", message),
+            Error::Calendar(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Database { source, .. } => Some(source),
+            Error::Parse { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// 予定の操作の失敗
+#[derive(Debug, PartialEq, Eq)]
+pub enum CalendarError {
+    /// 指定IDの予定が存在しない
+    NotFound(ScheduleId),
+    /// 指定IDの予定と時間が重複している
+    Conflict(ScheduleId),
+    /// 指定IDの予定に指定日時の発生がない
+    OccurrenceNotFound(ScheduleId, NaiveDateTime),
+    /// 予定として正しくない（問題点の一覧）
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for CalendarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalendarError::NotFound(id) => write!(f, "ID {} の予定が見つかりません", id),
+            CalendarError::Conflict(id) => write!(f, "ID {} の予定と重複しています", id),
+            CalendarError::OccurrenceNotFound(id, at) => {
+                write!(f, "ID {} の予定に {} 開始の回はありません", id, at)
+            }
+            CalendarError::Invalid(problems) => write!(f, "{}", problems.join("、")),
+        }
+    }
+}
+
+impl std::error::Error for CalendarError {}
+
+impl From<CalendarError> for Error {
+    fn from(error: CalendarError) -> Self {
+        Error::Calendar(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code() {
+        let parse = serde_json::from_str::<u64>("{").unwrap_err();
+        let codes: Vec<i32> = [
+            Error::FileNotFound(PathBuf::from("schedules.json")),
+            Error::Parse {
+                path: PathBuf::from("schedules.json"),
+                source: parse,
+            },
+            Error::Validation("不正な入力".to_string()),
+            CalendarError::Conflict(0).into(),
+            CalendarError::NotFound(0).into(),
+        ]
+        .iter()
+        .map(Error::exit_code)
+        .collect();
+        assert_eq!(codes, vec![3, 4, 5, 6, 7]);
+    }
+}
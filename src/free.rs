@@ -0,0 +1,173 @@
+use crate::{zone, Calendar};
+use chrono::{DateTime, Datelike, Days, Duration, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+
+/// 空き時間を探す条件
+#[derive(Debug, Clone, Copy)]
+pub struct FreeQuery {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// これより短い空き時間は除く
+    pub min: Duration,
+    /// 各日のこの時間帯の中だけを探す（`tz` での時刻）
+    pub within: Option<(NaiveTime, NaiveTime)>,
+    /// 土日を除く
+    pub skip_weekends: bool,
+    /// 日付・時間帯を解釈するタイムゾーン
+    pub tz: Tz,
+}
+
+/// 予定の入っていない時間を開始日時の昇順で返す（時間を占めない終日の予定は数えない）
+pub fn free_slots(calendar: &Calendar, query: &FreeQuery) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let busy: Vec<(DateTime<Utc>, DateTime<Utc>)> = calendar
+        .iter_range(query.from, query.to)
+        .filter(|(schedule, _)| schedule.is_busy())
+        .map(|(_, occurrence)| {
+            (
+                occurrence.start.with_timezone(&Utc),
+                occurrence.end.with_timezone(&Utc),
+            )
+        })
+        .collect();
+
+    let mut slots = Vec::new();
+    for (window_start, window_end) in windows(query) {
+        let mut cursor = window_start;
+        for &(start, end) in &busy {
+            if end <= cursor || start >= window_end {
+                continue;
+            }
+            if start > cursor {
+                slots.push((cursor, start));
+            }
+            cursor = cursor.max(end);
+        }
+        if cursor < window_end {
+            slots.push((cursor, window_end));
+        }
+    }
+    slots.retain(|(start, end)| *end - *start >= query.min);
+    slots
+}
+
+/// 探す対象の時間帯（隣り合うものはつなげる）
+fn windows(query: &FreeQuery) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    if query.within.is_none() && !query.skip_weekends {
+        return vec![(query.from, query.to)];
+    }
+    let tz = query.tz;
+    let first = query.from.with_timezone(&tz).date_naive();
+    let last = query.to.with_timezone(&tz).date_naive();
+    let mut windows: Vec<(DateTime<Utc>, DateTime<Utc>)> = Vec::new();
+    for date in first.iter_days().take_while(|date| *date <= last) {
+        if query.skip_weekends && matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+            continue;
+        }
+        let (start, end) = match query.within {
+            Some((start, end)) => (
+                zone::resolve_local(tz, date.and_time(start)).with_timezone(&Utc),
+                zone::resolve_local(tz, date.and_time(end)).with_timezone(&Utc),
+            ),
+            None => (
+                zone::start_of_day(tz, date),
+                zone::start_of_day(tz, date + Days::new(1)),
+            ),
+        };
+        let (start, end) = (start.max(query.from), end.min(query.to));
+        if start >= end {
+            continue;
+        }
+        match windows.last_mut() {
+            Some(previous) if previous.1 == start => previous.1 = end,
+            _ => windows.push((start, end)),
+        }
+    }
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util::native_date_time_from_str, Availability, Schedule};
+    use rstest::rstest;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        native_date_time_from_str(s).and_utc()
+    }
+
+    #[rstest]
+    // 期間全体から予定を除いた時間
+    #[case(None, false, 0, vec![
+        ("2024-02-09T00:00:00", "2024-02-09T09:00:00"),
+        ("2024-02-09T10:00:00", "2024-02-09T10:30:00"),
+        ("2024-02-09T12:00:00", "2024-02-12T00:00:00"),
+    ])]
+    // 短い空き時間を除く
+    #[case(None, false, 60, vec![
+        ("2024-02-09T00:00:00", "2024-02-09T09:00:00"),
+        ("2024-02-09T12:00:00", "2024-02-12T00:00:00"),
+    ])]
+    // 勤務時間内だけ、土日を除く
+    #[case(Some(("09:00:00", "18:00:00")), true, 0, vec![
+        ("2024-02-09T10:00:00", "2024-02-09T10:30:00"),
+        ("2024-02-09T12:00:00", "2024-02-09T18:00:00"),
+    ])]
+    // 土日を除く（平日は丸一日）
+    #[case(None, true, 0, vec![
+        ("2024-02-09T00:00:00", "2024-02-09T09:00:00"),
+        ("2024-02-09T10:00:00", "2024-02-09T10:30:00"),
+        ("2024-02-09T12:00:00", "2024-02-10T00:00:00"),
+    ])]
+    fn test_free_slots(
+        #[case] within: Option<(&str, &str)>,
+        #[case] skip_weekends: bool,
+        #[case] min_minutes: i64,
+        #[case] expected: Vec<(&str, &str)>,
+    ) {
+        let calendar = Calendar::new(
+            vec![
+                Schedule::new(
+                    0,
+                    "定例".to_string(),
+                    native_date_time_from_str("2024-02-09T09:00:00"),
+                    native_date_time_from_str("2024-02-09T10:00:00"),
+                ),
+                // 重なって保存されている予定もまとめて埋まっているとみなす
+                Schedule::new(
+                    1,
+                    "来客".to_string(),
+                    native_date_time_from_str("2024-02-09T10:30:00"),
+                    native_date_time_from_str("2024-02-09T11:30:00"),
+                ),
+                Schedule::new(
+                    2,
+                    "面談".to_string(),
+                    native_date_time_from_str("2024-02-09T11:00:00"),
+                    native_date_time_from_str("2024-02-09T12:00:00"),
+                ),
+                // 時間を占めない終日の予定は空き時間を埋めない
+                Schedule::new_all_day(
+                    3,
+                    "締め切り".to_string(),
+                    "2024-02-09".parse().unwrap(),
+                    "2024-02-09".parse().unwrap(),
+                    Availability::Free,
+                ),
+            ],
+            4,
+        );
+        let query = FreeQuery {
+            from: utc("2024-02-09T00:00:00"),
+            to: utc("2024-02-12T00:00:00"),
+            min: Duration::minutes(min_minutes),
+            within: within.map(|(start, end)| (start.parse().unwrap(), end.parse().unwrap())),
+            skip_weekends,
+            tz: Tz::UTC,
+        };
+        let expected: Vec<(DateTime<Utc>, DateTime<Utc>)> = expected
+            .iter()
+            .map(|(start, end)| (utc(start), utc(end)))
+            .collect();
+        assert_eq!(free_slots(&calendar, &query), expected);
+    }
+}
@@ -0,0 +1,1031 @@
+use crate::{
+    attendee::{Attendee, Rsvp},
+    recurrence::{Recurrence, Until, WeekdayNum},
+    schedule::generate_uid,
+    tag, zone, Availability, Calendar, Override, Schedule,
+};
+use chrono::{
+    DateTime, Datelike, Days, Duration, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone, Utc,
+};
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
+use std::{collections::BTreeMap, fmt};
+
+/// 1行の最大オクテット数（改行を除く）
+const MAX_LINE_OCTETS: usize = 75;
+
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+const DATE_FORMAT: &str = "%Y%m%d";
+
+/// 予定として取り込めなかった VEVENT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidEvent {
+    pub summary: String,
+    pub reason: String,
+}
+
+impl fmt::Display for InvalidEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.summary, self.reason)
+    }
+}
+
+/// コンテンツ行 `NAME;PARAM=VALUE:value` を分解したもの
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// iCalendar 形式の文字列から VEVENT を予定に変換する
+///
+/// ID はすべて 0 のまま返すので、取り込む側で払い出すこと。
+/// RECURRENCE-ID 付きの VEVENT は同じ UID の繰り返し予定の変更として取り込む。
+/// TZID は IANA のタイムゾーン名として解釈し、TZID も末尾の `Z` もない日時は `default_tz` の時刻とみなす。
+pub fn parse(input: &str, default_tz: Tz) -> Vec<Result<Schedule, InvalidEvent>> {
+    let mut results = Vec::new();
+    let mut overrides = Vec::new();
+    for event in events(input) {
+        let summary = find(&event, "SUMMARY")
+            .map(|property| unescape(&property.value))
+            .unwrap_or_default();
+        if let Some(recurrence_id) = find(&event, "RECURRENCE-ID") {
+            match parse_override(&event, recurrence_id, default_tz) {
+                Ok((uid, parsed)) => overrides.push((summary, uid, parsed)),
+                Err(reason) => results.push(Err(InvalidEvent { summary, reason })),
+            }
+        } else {
+            results.push(
+                parse_event(&event, summary.clone(), default_tz)
+                    .map_err(|reason| InvalidEvent { summary, reason }),
+            );
+        }
+    }
+
+    // 変更された回を同じ UID の繰り返し予定に紐付ける
+    for (summary, uid, parsed) in overrides {
+        let parent = results.iter_mut().find_map(|result| match result {
+            Ok(schedule) if schedule.uid == uid && schedule.recurrence.is_some() => Some(schedule),
+            _ => None,
+        });
+        match parent {
+            Some(parent) => {
+                // UTC で受け取った日時を親の予定のタイムゾーンに揃える
+                let tz = parent.tz();
+                parent.overrides.push(Override {
+                    recurrence_id: zone::convert(parsed.recurrence_id, Tz::UTC, tz),
+                    subject: parsed.subject.filter(|subject| *subject != parent.subject),
+                    start: zone::convert(parsed.start, Tz::UTC, tz),
+                    end: zone::convert(parsed.end, Tz::UTC, tz),
+                });
+                parent.overrides.sort_by_key(|o| o.recurrence_id);
+            }
+            None => results.push(Err(InvalidEvent {
+                summary,
+                reason: "RECURRENCE-ID に対応する繰り返し予定がありません".to_string(),
+            })),
+        }
+    }
+    results
+}
+
+/// VEVENT ごとのプロパティ一覧（VALARM など内側のコンポーネントは除く）
+fn events(input: &str) -> Vec<Vec<Property>> {
+    let mut events = Vec::new();
+    let mut stack: Vec<String> = Vec::new();
+    let mut current = Vec::new();
+    for line in unfold(input) {
+        let Some(property) = parse_line(&line) else {
+            continue;
+        };
+        match property.name.as_str() {
+            "BEGIN" => stack.push(property.value.to_ascii_uppercase()),
+            "END" => {
+                let ended = stack.pop();
+                if ended.as_deref() == Some("VEVENT") {
+                    events.push(std::mem::take(&mut current));
+                }
+            }
+            _ if stack.last().map(String::as_str) == Some("VEVENT") => current.push(property),
+            _ => {}
+        }
+    }
+    events
+}
+
+/// 折り返された行（空白またはタブで始まる行）を前の行に連結する
+fn unfold(input: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in input.lines() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn parse_line(line: &str) -> Option<Property> {
+    // 引用符の中の `:` や `;` は区切りとみなさない
+    let mut in_quotes = false;
+    let mut separators = Vec::new();
+    let mut value_start = None;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => separators.push(index),
+            ':' if !in_quotes => {
+                value_start = Some(index);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let value_start = value_start?;
+    let head = &line[..value_start];
+    let name_end = separators.first().copied().unwrap_or(head.len());
+    let params = separators
+        .iter()
+        .enumerate()
+        .map(|(i, &separator)| {
+            let end = separators.get(i + 1).copied().unwrap_or(head.len());
+            &head[separator + 1..end]
+        })
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| {
+            (
+                key.to_ascii_uppercase(),
+                value.trim_matches('"').to_string(),
+            )
+        })
+        .collect();
+    Some(Property {
+        name: head[..name_end].trim().to_ascii_uppercase(),
+        params,
+        value: line[value_start + 1..].to_string(),
+    })
+}
+
+/// TEXT 値のエスケープ (`\n`, `\,`, `\;`, `\\`) を戻す
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => result.push('\n'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// DATE (`20240101`) または DATE-TIME (`20240101T090000`, `20240101T090000Z`) を解釈する
+fn parse_date_time(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim().trim_end_matches('Z');
+    if value.len() == 8 {
+        NaiveDate::parse_from_str(value, DATE_FORMAT)
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+    } else {
+        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()
+    }
+}
+
+fn is_date(property: &Property) -> bool {
+    property
+        .param("VALUE")
+        .is_some_and(|value| value.eq_ignore_ascii_case("DATE"))
+        || property.value.trim().len() == 8
+}
+
+/// DURATION (`PT1H30M`, `P1D`, `P2W` など) を解釈する（表せないほど長いものは None）
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let value = value.strip_prefix('P')?;
+    let mut duration = Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' if !in_time && number.is_empty() => in_time = true,
+            unit => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+                let part = match (unit, in_time) {
+                    ('W', false) => Duration::try_weeks(n),
+                    ('D', false) => Duration::try_days(n),
+                    ('H', true) => Duration::try_hours(n),
+                    ('M', true) => Duration::try_minutes(n),
+                    ('S', true) => Duration::try_seconds(n),
+                    _ => None,
+                };
+                duration = duration.checked_add(&part?)?;
+            }
+        }
+    }
+    if !number.is_empty() {
+        return None;
+    }
+    Some(if negative { -duration } else { duration })
+}
+
+fn find<'a>(event: &'a [Property], name: &str) -> Option<&'a Property> {
+    event.iter().find(|property| property.name == name)
+}
+
+/// 日時の値を解釈し、そのタイムゾーン（末尾が `Z` なら UTC、TZID があればそれ、なければ `default_tz`）と組にする
+fn parse_zoned(
+    property: &Property,
+    value: &str,
+    default_tz: Tz,
+) -> Result<(NaiveDateTime, Tz), String> {
+    let local = parse_date_time(value)
+        .ok_or_else(|| format!("{} を解釈できません: {}", property.name, value))?;
+    let tz = if value.trim().ends_with('Z') {
+        Tz::UTC
+    } else if let Some(tzid) = property.param("TZID") {
+        tzid.parse()
+            .map_err(|_| format!("未対応の TZID です: {}", tzid))?
+    } else {
+        default_tz
+    };
+    Ok((local, tz))
+}
+
+/// DTSTART と DTEND/DURATION から開始・終了日時（DTSTART のタイムゾーンでの時刻）を求める
+fn parse_span(
+    event: &[Property],
+    default_tz: Tz,
+) -> Result<(NaiveDateTime, NaiveDateTime, Tz), String> {
+    let dtstart = find(event, "DTSTART").ok_or("DTSTART がありません")?;
+    let (start, tz) = parse_zoned(dtstart, &dtstart.value, default_tz)?;
+    let end = if let Some(dtend) = find(event, "DTEND") {
+        let (end, end_tz) = parse_zoned(dtend, &dtend.value, tz)?;
+        zone::convert(end, end_tz, tz)
+    } else if let Some(duration) = find(event, "DURATION") {
+        parse_duration(&duration.value)
+            .and_then(|length| start.checked_add_signed(length))
+            .ok_or_else(|| format!("DURATION を解釈できません: {}", duration.value))?
+    } else if is_date(dtstart) {
+        // 終了のない終日予定はその日いっぱい
+        start + Duration::days(1)
+    } else {
+        start
+    };
+    Ok((start, end, tz))
+}
+
+fn parse_event(event: &[Property], summary: String, default_tz: Tz) -> Result<Schedule, String> {
+    let (start, end, tz) = parse_span(event, default_tz)?;
+    let recurrence = find(event, "RRULE")
+        .map(|rrule| rrule.value.parse::<Recurrence>())
+        .transpose()
+        .map_err(|error| error.to_string())?;
+    let mut exdates = Vec::new();
+    for exdate in event.iter().filter(|property| property.name == "EXDATE") {
+        for value in exdate.value.split(',') {
+            let (date, exdate_tz) = parse_zoned(exdate, value, tz)?;
+            // 日付のみの EXDATE はその日の回を指す
+            exdates.push(if value.trim().len() == 8 {
+                date.date().and_time(start.time())
+            } else {
+                zone::convert(date, exdate_tz, tz)
+            });
+        }
+    }
+    exdates.sort();
+    // 使えない文字を含むカテゴリは取り込まない
+    let mut tags: Vec<String> = Vec::new();
+    for categories in event
+        .iter()
+        .filter(|property| property.name == "CATEGORIES")
+    {
+        for tag in categories
+            .value
+            .split(',')
+            .filter_map(|value| tag::normalize(&unescape(value)).ok())
+        {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    let text = |name: &str| {
+        find(event, name)
+            .map(|property| unescape(&property.value))
+            .filter(|value| !value.is_empty())
+    };
+    // mailto: 以外の参加者は取り込まない
+    let attendees = event
+        .iter()
+        .filter(|property| property.name == "ATTENDEE")
+        .filter_map(parse_attendee)
+        .collect();
+    // 日付のみの DTSTART は終日の予定で、TRANSP がなければ時間を占める (RFC 5545)
+    let all_day = find(event, "DTSTART")
+        .filter(|dtstart| is_date(dtstart))
+        .map(|_| match find(event, "TRANSP") {
+            Some(transp) if transp.value.trim().eq_ignore_ascii_case("TRANSPARENT") => {
+                Availability::Free
+            }
+            _ => Availability::Busy,
+        });
+    let uid = find(event, "UID")
+        .map(|uid| uid.value.trim().to_string())
+        .filter(|uid| !uid.is_empty())
+        .unwrap_or_else(generate_uid);
+    Ok(Schedule {
+        uid,
+        tz: Some(tz),
+        recurrence,
+        exdates,
+        tags,
+        location: text("LOCATION"),
+        description: text("DESCRIPTION"),
+        attendees,
+        all_day,
+        ..Schedule::new(0, summary, start, end)
+    })
+}
+
+/// `ATTENDEE;CN=名前;PARTSTAT=ACCEPTED:mailto:メールアドレス` を参加者に変換する
+///
+/// 出欠の返事がない、または DELEGATED など扱えないものは未回答とする。
+fn parse_attendee(property: &Property) -> Option<Attendee> {
+    let value = property.value.trim();
+    let email = value
+        .get(..7)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("mailto:"))
+        .map(|_| value[7..].trim())
+        .filter(|email| email.contains('@'))?;
+    Some(Attendee {
+        name: property.param("CN").unwrap_or_default().to_string(),
+        email: email.to_string(),
+        status: property
+            .param("PARTSTAT")
+            .and_then(|status| status.parse().ok())
+            .unwrap_or_default(),
+    })
+}
+
+/// RECURRENCE-ID 付きの VEVENT を (親の UID, 変更内容) に変換する
+///
+/// 親の予定のタイムゾーンはまだ分からないので、日時はすべて UTC で返す。
+fn parse_override(
+    event: &[Property],
+    recurrence_id: &Property,
+    default_tz: Tz,
+) -> Result<(String, Override), String> {
+    let uid = find(event, "UID").ok_or("UID がありません")?.value.trim();
+    let (start, end, tz) = parse_span(event, default_tz)?;
+    let (recurrence_id, recurrence_id_tz) =
+        parse_zoned(recurrence_id, &recurrence_id.value, default_tz)?;
+    Ok((
+        uid.to_string(),
+        Override {
+            recurrence_id: zone::convert(recurrence_id, recurrence_id_tz, Tz::UTC),
+            subject: find(event, "SUMMARY").map(|summary| unescape(&summary.value)),
+            start: zone::convert(start, tz, Tz::UTC),
+            end: zone::convert(end, tz, Tz::UTC),
+        },
+    ))
+}
+
+/// 予定を VCALENDAR 形式の文字列に変換する
+///
+/// UTC の予定は末尾に `Z` を付けた時刻で、それ以外は IANA のタイムゾーン名を TZID に指定して書き出し、
+/// TZID ごとにその定義 (VTIMEZONE) を付ける。
+/// 終日の予定は日付のみ (`VALUE=DATE`) で書き出し、時間を占めないものには `TRANSP:TRANSPARENT` を付ける。
+pub fn write(calendar: &Calendar, dtstamp: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//simple_calender//calendar//JA".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    // TZID で参照するタイムゾーンと、そのタイムゾーンの最初の予定の年
+    let mut zones: BTreeMap<&str, (Tz, i32)> = BTreeMap::new();
+    for schedule in calendar.schedules() {
+        let tz = schedule.tz();
+        if schedule.is_all_day() || tz == Tz::UTC {
+            continue;
+        }
+        let year = schedule.start.year();
+        zones
+            .entry(tz.name())
+            .and_modify(|(_, first)| *first = year.min(*first))
+            .or_insert((tz, year));
+    }
+    // 年の初めの予定も切り替わりの後になるよう、前年の切り替わりから書き出す
+    for (tz, year) in zones.into_values() {
+        lines.extend(timezone_lines(tz, year - 1));
+    }
+    let dtstamp = format!("DTSTAMP:{}", dtstamp.format("%Y%m%dT%H%M%SZ"));
+    for schedule in calendar.schedules() {
+        let tz = schedule.tz();
+        let line = |name: &str, values: &[NaiveDateTime]| {
+            if schedule.is_all_day() {
+                date_line(name, values)
+            } else {
+                date_time_line(name, values, tz)
+            }
+        };
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", schedule.uid));
+        lines.push(dtstamp.clone());
+        lines.push(format!("SUMMARY:{}", escape(&schedule.subject)));
+        lines.push(line("DTSTART", &[schedule.start]));
+        lines.push(line("DTEND", &[schedule.end]));
+        if schedule.all_day == Some(Availability::Free) {
+            lines.push("TRANSP:TRANSPARENT".to_string());
+        }
+        if let Some(rule) = &schedule.recurrence {
+            lines.push(rrule_line(rule, schedule));
+        }
+        if !schedule.exdates.is_empty() {
+            lines.push(line("EXDATE", &schedule.exdates));
+        }
+        if !schedule.tags.is_empty() {
+            let tags: Vec<String> = schedule.tags.iter().map(|tag| escape(tag)).collect();
+            lines.push(format!("CATEGORIES:{}", tags.join(",")));
+        }
+        if let Some(location) = &schedule.location {
+            lines.push(format!("LOCATION:{}", escape(location)));
+        }
+        if let Some(description) = &schedule.description {
+            lines.push(format!("DESCRIPTION:{}", escape(description)));
+        }
+        for attendee in &schedule.attendees {
+            lines.push(attendee_line(attendee));
+        }
+        lines.push("END:VEVENT".to_string());
+
+        // 1回分だけ変更した発生は RECURRENCE-ID 付きの VEVENT として書き出す
+        for o in &schedule.overrides {
+            let subject = o.subject.as_deref().unwrap_or(&schedule.subject);
+            lines.push("BEGIN:VEVENT".to_string());
+            lines.push(format!("UID:{}", schedule.uid));
+            lines.push(dtstamp.clone());
+            lines.push(line("RECURRENCE-ID", &[o.recurrence_id]));
+            lines.push(format!("SUMMARY:{}", escape(subject)));
+            lines.push(line("DTSTART", &[o.start]));
+            lines.push(line("DTEND", &[o.end]));
+            lines.push("END:VEVENT".to_string());
+        }
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut output = String::new();
+    for line in lines {
+        fold(&mut output, &line);
+    }
+    output
+}
+
+/// `tz` の定義 (VTIMEZONE) の行
+///
+/// `year` の年の UTC オフセットの切り替わりを書き出す。年に2回（夏時間の始まりと終わり）切り替わる
+/// なら、以後も毎年同じ月の同じ週の同じ曜日に切り替わるものとして RRULE を付ける。
+fn timezone_lines(tz: Tz, year: i32) -> Vec<String> {
+    let mut lines = vec!["BEGIN:VTIMEZONE".to_string(), format!("TZID:{}", tz.name())];
+    let first = NaiveDate::from_ymd_opt(year, 1, 1)
+        .unwrap()
+        .and_time(NaiveTime::MIN);
+    let transitions = offset_transitions(tz, year);
+    if transitions.is_empty() {
+        let offset = tz.offset_from_utc_datetime(&first);
+        lines.extend(observance(
+            "STANDARD",
+            first,
+            offset.fix().local_minus_utc(),
+            &offset,
+            None,
+        ));
+    }
+    for &(at, before) in &transitions {
+        let after = tz.offset_from_utc_datetime(&at);
+        let local = at + Duration::seconds(before.into());
+        let kind = if after.dst_offset().is_zero() {
+            "STANDARD"
+        } else {
+            "DAYLIGHT"
+        };
+        let rule = (transitions.len() == 2).then(|| {
+            let date = local.date();
+            // 月の最後の週なら、第5週がない年もあるので「最終」とする
+            let last_week = (date + Days::new(7)).month() != date.month();
+            let day = WeekdayNum {
+                ordinal: Some(if last_week {
+                    -1
+                } else {
+                    (date.day() as i32 - 1) / 7 + 1
+                }),
+                weekday: date.weekday(),
+            };
+            format!("RRULE:FREQ=YEARLY;BYMONTH={};BYDAY={}", date.month(), day)
+        });
+        lines.extend(observance(kind, local, before, &after, rule));
+    }
+    lines.push("END:VTIMEZONE".to_string());
+    lines
+}
+
+/// `year` の年に `tz` の UTC オフセットが切り替わる UTC の日時と、切り替わる前のオフセット（秒）
+fn offset_transitions(tz: Tz, year: i32) -> Vec<(NaiveDateTime, i32)> {
+    let offset_at = |at: NaiveDateTime| tz.offset_from_utc_datetime(&at).fix().local_minus_utc();
+    let mut transitions = Vec::new();
+    let mut at = NaiveDate::from_ymd_opt(year, 1, 1)
+        .unwrap()
+        .and_time(NaiveTime::MIN);
+    while at.year() == year {
+        let Some(next) = at.checked_add_signed(Duration::hours(1)) else {
+            break;
+        };
+        let before = offset_at(at);
+        if offset_at(next) != before {
+            // 30分単位のオフセットもあるので、1時間の中で切り替わる分を探す
+            let switched = (1..=60)
+                .map(|minutes| at + Duration::minutes(minutes))
+                .find(|at| offset_at(*at) != before)
+                .unwrap();
+            transitions.push((switched, before));
+        }
+        at = next;
+    }
+    transitions
+}
+
+/// VTIMEZONE の中の STANDARD または DAYLIGHT の行（`start` は切り替わる前のオフセットでの日時）
+fn observance(
+    kind: &str,
+    start: NaiveDateTime,
+    from: i32,
+    to: &<Tz as TimeZone>::Offset,
+    rule: Option<String>,
+) -> Vec<String> {
+    let mut lines = vec![
+        format!("BEGIN:{}", kind),
+        format!("DTSTART:{}", start.format(DATE_TIME_FORMAT)),
+        format!("TZOFFSETFROM:{}", utc_offset(from)),
+        format!("TZOFFSETTO:{}", utc_offset(to.fix().local_minus_utc())),
+    ];
+    if let Some(name) = to.abbreviation() {
+        lines.push(format!("TZNAME:{}", escape(name)));
+    }
+    lines.extend(rule);
+    lines.push(format!("END:{}", kind));
+    lines
+}
+
+/// UTC オフセット（秒）を `+0900` や `-0330` の形式にする
+fn utc_offset(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.unsigned_abs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if seconds == 0 {
+        format!("{}{:02}{:02}", sign, hours, minutes)
+    } else {
+        format!("{}{:02}{:02}{:02}", sign, hours, minutes, seconds)
+    }
+}
+
+/// `tz` での日時を値に持つプロパティの行
+fn date_time_line(name: &str, values: &[NaiveDateTime], tz: Tz) -> String {
+    let (params, suffix) = match tz {
+        Tz::UTC => (String::new(), "Z"),
+        tz => (format!(";TZID={}", tz.name()), ""),
+    };
+    let values: Vec<String> = values
+        .iter()
+        .map(|value| format!("{}{}", value.format(DATE_TIME_FORMAT), suffix))
+        .collect();
+    format!("{}{}:{}", name, params, values.join(","))
+}
+
+/// RRULE の行
+///
+/// RFC 5545 に従い、UNTIL は終日の予定なら日付、それ以外は UTC の日時で書き出す。
+fn rrule_line(rule: &Recurrence, schedule: &Schedule) -> String {
+    let tz = schedule.tz();
+    let until = rule.until.map(|until| {
+        let instant = until.instant(tz);
+        if schedule.is_all_day() {
+            Until::Date(instant.with_timezone(&tz).date_naive())
+        } else {
+            Until::Utc(instant.naive_utc())
+        }
+    });
+    let rule = Recurrence {
+        until,
+        ..rule.clone()
+    };
+    format!("RRULE:{}", rule)
+}
+
+/// 日付のみを値に持つプロパティの行（終日の予定）
+fn date_line(name: &str, values: &[NaiveDateTime]) -> String {
+    let values: Vec<String> = values
+        .iter()
+        .map(|value| value.format(DATE_FORMAT).to_string())
+        .collect();
+    format!("{};VALUE=DATE:{}", name, values.join(","))
+}
+
+/// 参加者のプロパティの行（名前の `"` は引用符の中に書けないので除く）
+fn attendee_line(attendee: &Attendee) -> String {
+    let mut line = "ATTENDEE".to_string();
+    if !attendee.name.is_empty() {
+        line.push_str(&format!(";CN=\"{}\"", attendee.name.replace('"', "")));
+    }
+    if attendee.status != Rsvp::NeedsAction {
+        line.push_str(&format!(
+            ";PARTSTAT={}",
+            attendee.status.as_str().to_ascii_uppercase()
+        ));
+    }
+    format!("{}:mailto:{}", line, attendee.email)
+}
+
+/// TEXT 値の `\`, `;`, `,`, 改行をエスケープする
+fn escape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            ';' => result.push_str("\\;"),
+            ',' => result.push_str("\\,"),
+            '\n' => result.push_str("\\n"),
+            '\r' => {}
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// 1行を75オクテットごとに折り返して CRLF 付きで書き出す（マルチバイト文字の途中では切らない）
+fn fold(output: &mut String, line: &str) {
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            output.push_str("\r\n ");
+            // 継続行の先頭の空白も1オクテットに数える
+            octets = 1;
+        }
+        output.push(c);
+        octets += c.len_utf8();
+    }
+    output.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::native_date_time_from_str;
+    use chrono::NaiveTime;
+    use rstest::rstest;
+
+    #[test]
+    fn test_parse() {
+        let input = "BEGIN:VCALENDAR\r\n\
+            VERSION:2.0\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:standup@example.com\r\n\
+            SUMMARY:朝会\\, 全体\r\n\
+            DTSTART;TZID=Asia/Tokyo:20240101T090000\r\n\
+            DURATION:PT15M\r\n\
+            RRULE:FREQ=WEEKLY;BYDAY=MO,\r\n TU\r\n\
+            EXDATE;TZID=Asia/Tokyo:20240102T090000\r\n\
+            ATTENDEE;CN=\"Yamada: Taro\";PARTSTAT=DELEGATED:MAILTO:taro@example.com\r\n\
+            ATTENDEE;CUTYPE=ROOM:urn:uuid:room-a\r\n\
+            BEGIN:VALARM\r\n\
+            SUMMARY:通知\r\n\
+            END:VALARM\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:standup@example.com\r\n\
+            RECURRENCE-ID;TZID=Asia/Tokyo:20240108T090000\r\n\
+            SUMMARY:朝会\\, 全体\r\n\
+            DTSTART:20240108T010000Z\r\n\
+            DTEND;TZID=Asia/Tokyo:20240108T101500\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            SUMMARY:開始日時なし\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+        let results = parse(input, Tz::UTC);
+        assert_eq!(results.len(), 2);
+
+        let schedule = results[0].as_ref().unwrap();
+        assert_eq!(schedule.uid, "standup@example.com");
+        assert_eq!(schedule.subject, "朝会, 全体");
+        assert_eq!(schedule.tz, Some(chrono_tz::Asia::Tokyo));
+        assert_eq!(
+            schedule.start,
+            native_date_time_from_str("2024-01-01T09:00:00")
+        );
+        assert_eq!(
+            schedule.end,
+            native_date_time_from_str("2024-01-01T09:15:00")
+        );
+        assert_eq!(
+            schedule.recurrence.as_ref().unwrap().to_string(),
+            "FREQ=WEEKLY;BYDAY=MO,TU"
+        );
+        assert_eq!(
+            schedule.exdates,
+            vec![native_date_time_from_str("2024-01-02T09:00:00")]
+        );
+        assert_eq!(
+            schedule.overrides,
+            vec![Override {
+                recurrence_id: native_date_time_from_str("2024-01-08T09:00:00"),
+                subject: None,
+                start: native_date_time_from_str("2024-01-08T10:00:00"),
+                end: native_date_time_from_str("2024-01-08T10:15:00"),
+            }]
+        );
+        assert_eq!(
+            schedule.attendees,
+            vec![Attendee {
+                name: "Yamada: Taro".to_string(),
+                email: "taro@example.com".to_string(),
+                status: Rsvp::NeedsAction,
+            }]
+        );
+
+        let invalid = results[1].as_ref().unwrap_err();
+        assert_eq!(invalid.summary, "開始日時なし");
+    }
+
+    #[rstest]
+    #[case("PT1H30M", Some(Duration::minutes(90)))]
+    #[case("P1DT12H", Some(Duration::hours(36)))]
+    #[case("P2W", Some(Duration::weeks(2)))]
+    #[case("-PT15M", Some(Duration::minutes(-15)))]
+    #[case("PT1H30", None)]
+    #[case("1H", None)]
+    #[case("P999999999999999W", None)]
+    #[case("PT9223372036854775807S", None)]
+    fn test_parse_duration(#[case] value: &str, #[case] expected: Option<Duration>) {
+        assert_eq!(parse_duration(value), expected);
+    }
+
+    #[rstest]
+    #[case("P999999999999999W")]
+    #[case("P99999999D")]
+    fn test_parse_rejects_too_long_duration(#[case] duration: &str) {
+        let input = format!(
+            "BEGIN:VCALENDAR\r\n\
+            BEGIN:VEVENT\r\n\
+            SUMMARY:長すぎる予定\r\n\
+            DTSTART:20240101T090000Z\r\n\
+            DURATION:{}\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n",
+            duration
+        );
+        let results = parse(&input, Tz::UTC);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].as_ref().unwrap_err().summary, "長すぎる予定");
+    }
+
+    #[rstest]
+    #[case("a\\nb", "a\nb")]
+    #[case("a\\;b\\,c", "a;b,c")]
+    #[case("C:\\\\tmp", "C:\\tmp")]
+    fn test_unescape(#[case] value: &str, #[case] expected: &str) {
+        assert_eq!(unescape(value), expected);
+    }
+
+    #[test]
+    fn test_write_round_trip() {
+        let mut schedule = Schedule {
+            tz: Some(chrono_tz::America::New_York),
+            recurrence: Some("FREQ=WEEKLY;BYDAY=MO;COUNT=5".parse().unwrap()),
+            exdates: vec![native_date_time_from_str("2024-01-08T09:00:00")],
+            tags: vec!["work".to_string(), "定例".to_string()],
+            location: Some("本社 3F, 会議室A".to_string()),
+            description: Some("議題:\n- 進捗; 課題".to_string()),
+            attendees: vec![
+                Attendee {
+                    name: "Yamada, Taro".to_string(),
+                    email: "taro@example.com".to_string(),
+                    status: Rsvp::Accepted,
+                },
+                Attendee {
+                    name: String::new(),
+                    email: "hanako@example.com".to_string(),
+                    status: Rsvp::NeedsAction,
+                },
+            ],
+            ..Schedule::new(
+                0,
+                "週次定例; 議題は\nWiki 参照, 必ず確認".repeat(3),
+                native_date_time_from_str("2024-01-01T09:00:00"),
+                native_date_time_from_str("2024-01-01T10:00:00"),
+            )
+        };
+        schedule.overrides.push(Override {
+            recurrence_id: native_date_time_from_str("2024-01-15T09:00:00"),
+            subject: Some("定例（延期）".to_string()),
+            start: native_date_time_from_str("2024-01-16T09:00:00"),
+            end: native_date_time_from_str("2024-01-16T10:00:00"),
+        });
+        let calendar = Calendar::new(vec![schedule.clone()], 1);
+        let dtstamp = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let output = write(&calendar, dtstamp);
+
+        assert!(output.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(output.contains("DTSTAMP:20240101T000000Z\r\n"));
+        for line in output.split("\r\n") {
+            assert!(line.len() <= MAX_LINE_OCTETS, "{}", line);
+        }
+
+        assert!(output.contains("DTSTART;TZID=America/New_York:20240101T090000\r\n"));
+        assert!(output.contains(
+            "BEGIN:VTIMEZONE\r\n\
+            TZID:America/New_York\r\n\
+            BEGIN:DAYLIGHT\r\n\
+            DTSTART:20230312T020000\r\n\
+            TZOFFSETFROM:-0500\r\n\
+            TZOFFSETTO:-0400\r\n\
+            TZNAME:EDT\r\n\
+            RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU\r\n\
+            END:DAYLIGHT\r\n\
+            BEGIN:STANDARD\r\n\
+            DTSTART:20231105T020000\r\n\
+            TZOFFSETFROM:-0400\r\n\
+            TZOFFSETTO:-0500\r\n\
+            TZNAME:EST\r\n\
+            RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU\r\n\
+            END:STANDARD\r\n\
+            END:VTIMEZONE\r\n"
+        ));
+
+        let parsed: Vec<Schedule> = parse(&output, Tz::UTC)
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(parsed, vec![schedule]);
+    }
+
+    #[rstest]
+    #[case("20240105T120000", None, "RRULE:FREQ=DAILY;UNTIL=20240105T030000Z\r\n")]
+    #[case("20240105", None, "RRULE:FREQ=DAILY;UNTIL=20240105T145959Z\r\n")]
+    #[case(
+        "20240105T030000Z",
+        Some(Availability::Busy),
+        "RRULE:FREQ=DAILY;UNTIL=20240105\r\n"
+    )]
+    fn test_write_until(
+        #[case] until: &str,
+        #[case] all_day: Option<Availability>,
+        #[case] expected: &str,
+    ) {
+        let time = if all_day.is_some() { "00:00" } else { "12:00" };
+        let schedule = Schedule {
+            tz: Some(chrono_tz::Asia::Tokyo),
+            recurrence: Some(format!("FREQ=DAILY;UNTIL={}", until).parse().unwrap()),
+            all_day,
+            ..Schedule::new(
+                0,
+                "朝会".to_string(),
+                native_date_time_from_str(&format!("2024-01-01T{}:00", time)),
+                native_date_time_from_str("2024-01-02T00:00:00"),
+            )
+        };
+        let output = write(
+            &Calendar::new(vec![schedule.clone()], 1),
+            DateTime::<Utc>::UNIX_EPOCH,
+        );
+        assert!(output.contains(expected), "{}", output);
+
+        // 書き出した UNTIL を取り込んでも同じ回が繰り返される
+        let parsed = parse(&output, chrono_tz::Asia::Tokyo).remove(0).unwrap();
+        let starts = |schedule: &Schedule| {
+            schedule
+                .regular_occurrences()
+                .map(|occurrence| occurrence.start)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(starts(&parsed), starts(&schedule));
+        assert_eq!(starts(&parsed).len(), 5);
+    }
+
+    #[rstest]
+    // 夏時間のないタイムゾーン
+    #[case(chrono_tz::Asia::Tokyo, vec![
+        "BEGIN:STANDARD",
+        "DTSTART:20240101T000000",
+        "TZOFFSETFROM:+0900",
+        "TZOFFSETTO:+0900",
+        "TZNAME:JST",
+        "END:STANDARD",
+    ])]
+    // 南半球で30分単位のオフセットのタイムゾーン
+    #[case(chrono_tz::Australia::Adelaide, vec![
+        "BEGIN:STANDARD",
+        "DTSTART:20240407T030000",
+        "TZOFFSETFROM:+1030",
+        "TZOFFSETTO:+0930",
+        "TZNAME:ACST",
+        "RRULE:FREQ=YEARLY;BYMONTH=4;BYDAY=1SU",
+        "END:STANDARD",
+        "BEGIN:DAYLIGHT",
+        "DTSTART:20241006T020000",
+        "TZOFFSETFROM:+0930",
+        "TZOFFSETTO:+1030",
+        "TZNAME:ACDT",
+        "RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=1SU",
+        "END:DAYLIGHT",
+    ])]
+    fn test_timezone_lines(#[case] tz: Tz, #[case] observances: Vec<&str>) {
+        let lines = timezone_lines(tz, 2024);
+        assert_eq!(
+            lines[..2],
+            ["BEGIN:VTIMEZONE".to_string(), format!("TZID:{}", tz.name())]
+        );
+        assert_eq!(lines[2..lines.len() - 1], observances);
+        assert_eq!(lines.last().unwrap(), "END:VTIMEZONE");
+    }
+
+    #[test]
+    fn test_all_day_round_trip() {
+        let tz = chrono_tz::Asia::Tokyo;
+        let date = |s: &str| s.parse::<NaiveDate>().unwrap();
+        let schedules = vec![
+            Schedule {
+                tz: Some(tz),
+                ..Schedule::new_all_day(
+                    0,
+                    "出張".to_string(),
+                    date("2024-01-10"),
+                    date("2024-01-12"),
+                    Availability::Busy,
+                )
+            },
+            Schedule {
+                tz: Some(tz),
+                recurrence: Some("FREQ=YEARLY;COUNT=3".parse().unwrap()),
+                exdates: vec![date("2025-01-01").and_time(NaiveTime::MIN)],
+                ..Schedule::new_all_day(
+                    1,
+                    "元日".to_string(),
+                    date("2024-01-01"),
+                    date("2024-01-01"),
+                    Availability::Free,
+                )
+            },
+        ];
+        let output = write(
+            &Calendar::new(schedules.clone(), 2),
+            DateTime::<Utc>::UNIX_EPOCH,
+        );
+        assert!(output.contains("DTSTART;VALUE=DATE:20240110\r\nDTEND;VALUE=DATE:20240113\r\n"));
+        assert!(output.contains("TRANSP:TRANSPARENT\r\n"));
+        assert!(output.contains("EXDATE;VALUE=DATE:20250101\r\n"));
+
+        // 日付のみの予定は取り込む側のタイムゾーンの日付になる
+        let parsed: Vec<Schedule> = parse(&output, tz)
+            .into_iter()
+            .zip(0..)
+            .map(|(result, id)| Schedule {
+                id,
+                ..result.unwrap()
+            })
+            .collect();
+        assert_eq!(parsed, schedules);
+    }
+
+    #[rstest]
+    #[case("a;b,c", "a\\;b\\,c")]
+    #[case("1行目\n2行目", "1行目\\n2行目")]
+    #[case("C:\\tmp", "C:\\\\tmp")]
+    fn test_escape(#[case] value: &str, #[case] expected: &str) {
+        assert_eq!(escape(value), expected);
+        assert_eq!(unescape(&escape(value)), value);
+    }
+}
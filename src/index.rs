@@ -0,0 +1,212 @@
+use crate::{Schedule, ScheduleId};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+
+/// 予定を期間で探すための索引（カレンダー内の予定の位置を持つ）
+///
+/// 単発の予定は開始日時の順に並べて二分探索する。終了日時は開始日時から最も長い予定の
+/// 長さまでしか離れないので、`[from, to)` と重なりうるのは開始日時が `from - 最長` より後で
+/// `to` より前のものに限られる。繰り返し予定は最初の発生の開始と最後の発生の終了を覚えておき、
+/// 1件ずつ調べる。IDからもすぐに位置を引けるようにする。
+#[derive(Debug, Clone, Default)]
+pub struct Index {
+    /// 単発の予定の（開始日時, 終了日時, 位置）を開始日時と位置の昇順に並べたもの
+    singles: Vec<(DateTime<Utc>, DateTime<Utc>, usize)>,
+    /// 単発の予定の長さの上限（予定を削除しても縮めない）
+    longest: Duration,
+    /// 繰り返し予定の（最初の発生の開始日時, 最後の発生の終了日時, 位置）
+    recurring: Vec<(DateTime<Utc>, Option<DateTime<Utc>>, usize)>,
+    /// 予定のIDと位置（同じIDの予定が複数あれば最初のもの）
+    positions: HashMap<ScheduleId, usize>,
+}
+
+impl Index {
+    /// `schedules` の索引を作る
+    pub fn build(schedules: &[Schedule]) -> Index {
+        let mut index = Index::default();
+        for (position, schedule) in schedules.iter().enumerate() {
+            index.positions.entry(schedule.id).or_insert(position);
+            match schedule.recurrence {
+                None => {
+                    let entry = index.single_entry(position, schedule);
+                    index.singles.push(entry);
+                }
+                Some(_) => index.recurring.push(recurring_entry(position, schedule)),
+            }
+        }
+        index
+            .singles
+            .sort_unstable_by_key(|(start, _, position)| (*start, *position));
+        index
+    }
+
+    /// `position` にある予定を索引に加える
+    pub fn insert(&mut self, position: usize, schedule: &Schedule) {
+        self.positions.entry(schedule.id).or_insert(position);
+        match schedule.recurrence {
+            None => {
+                let entry = self.single_entry(position, schedule);
+                let at = self
+                    .singles
+                    .partition_point(|e| (e.0, e.2) < (entry.0, entry.2));
+                self.singles.insert(at, entry);
+            }
+            Some(_) => self.recurring.push(recurring_entry(position, schedule)),
+        }
+    }
+
+    /// `position` にある予定（`schedule`）を索引から除く
+    pub fn remove(&mut self, position: usize, schedule: &Schedule) {
+        if self.positions.get(&schedule.id) == Some(&position) {
+            self.positions.remove(&schedule.id);
+        }
+        match schedule.recurrence {
+            None => {
+                let key = (schedule.first_start(), position);
+                let at = self.singles.partition_point(|e| (e.0, e.2) < key);
+                if self.singles.get(at).is_some_and(|e| (e.0, e.2) == key) {
+                    self.singles.remove(at);
+                }
+            }
+            Some(_) => self.recurring.retain(|e| e.2 != position),
+        }
+    }
+
+    /// `position` の予定を取り除いたので、それより後ろの位置を詰める
+    pub fn close_gap(&mut self, position: usize) {
+        for entry in &mut self.singles {
+            if entry.2 > position {
+                entry.2 -= 1;
+            }
+        }
+        for entry in &mut self.recurring {
+            if entry.2 > position {
+                entry.2 -= 1;
+            }
+        }
+        for entry in self.positions.values_mut() {
+            if *entry > position {
+                *entry -= 1;
+            }
+        }
+    }
+
+    /// 指定IDの予定の位置
+    pub fn position(&self, id: ScheduleId) -> Option<usize> {
+        self.positions.get(&id).copied()
+    }
+
+    /// `[from, to)` と重なる発生がありうる予定の位置を昇順で返す
+    pub fn query(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<usize> {
+        let lower = from
+            .checked_sub_signed(self.longest)
+            .unwrap_or(DateTime::<Utc>::MIN_UTC);
+        let first = self.singles.partition_point(|e| e.0 <= lower);
+        let last = self.singles.partition_point(|e| e.0 < to).max(first);
+        let mut positions: Vec<usize> = self.singles[first..last]
+            .iter()
+            .filter(|(_, end, _)| *end > from)
+            .map(|(_, _, position)| *position)
+            .chain(
+                self.recurring
+                    .iter()
+                    .filter(|(start, end, _)| *start < to && end.is_none_or(|end| end > from))
+                    .map(|(_, _, position)| *position),
+            )
+            .collect();
+        positions.sort_unstable();
+        positions
+    }
+
+    /// 単発の予定の索引の項目を作り、長さの上限を広げる
+    fn single_entry(
+        &mut self,
+        position: usize,
+        schedule: &Schedule,
+    ) -> (DateTime<Utc>, DateTime<Utc>, usize) {
+        let start = schedule.first_start();
+        let end = schedule.last_end().unwrap_or(start);
+        self.longest = self.longest.max(end - start);
+        (start, end, position)
+    }
+}
+
+fn recurring_entry(
+    position: usize,
+    schedule: &Schedule,
+) -> (DateTime<Utc>, Option<DateTime<Utc>>, usize) {
+    (schedule.first_start(), schedule.last_end(), position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::native_date_time_from_str;
+    use rstest::rstest;
+
+    fn schedules() -> Vec<Schedule> {
+        vec![
+            Schedule::new(
+                0,
+                "長い予定".to_string(),
+                native_date_time_from_str("2024-01-01T09:00:00"),
+                native_date_time_from_str("2024-01-05T09:00:00"),
+            ),
+            Schedule {
+                recurrence: Some("FREQ=WEEKLY;COUNT=2".parse().unwrap()),
+                ..Schedule::new(
+                    1,
+                    "定例".to_string(),
+                    native_date_time_from_str("2024-01-02T13:00:00"),
+                    native_date_time_from_str("2024-01-02T14:00:00"),
+                )
+            },
+            Schedule::new(
+                2,
+                "短い予定".to_string(),
+                native_date_time_from_str("2024-01-03T09:00:00"),
+                native_date_time_from_str("2024-01-03T10:00:00"),
+            ),
+        ]
+    }
+
+    #[rstest]
+    // 前に始まった長い予定も見つける
+    #[case("2024-01-04T00:00:00", "2024-01-04T01:00:00", vec![0, 1])]
+    #[case("2024-01-03T09:30:00", "2024-01-03T09:45:00", vec![0, 1, 2])]
+    // 終了日時ちょうどからの期間とは重ならない
+    #[case("2024-01-05T09:00:00", "2024-01-09T13:00:00", vec![1])]
+    #[case("2024-01-09T14:00:00", "2024-02-01T00:00:00", vec![])]
+    fn test_index_query(#[case] from: &str, #[case] to: &str, #[case] expected: Vec<usize>) {
+        let (from, to) = (
+            native_date_time_from_str(from).and_utc(),
+            native_date_time_from_str(to).and_utc(),
+        );
+        let schedules = schedules();
+        assert_eq!(Index::build(&schedules).query(from, to), expected);
+
+        // 1件ずつ加えても同じ
+        let mut index = Index::default();
+        for (position, schedule) in schedules.iter().enumerate() {
+            index.insert(position, schedule);
+        }
+        assert_eq!(index.query(from, to), expected);
+    }
+
+    #[test]
+    fn test_index_remove() {
+        let schedules = schedules();
+        let mut index = Index::build(&schedules);
+        index.remove(0, &schedules[0]);
+        index.close_gap(0);
+        assert_eq!(index.position(0), None);
+        assert_eq!(index.position(2), Some(1));
+        assert_eq!(
+            index.query(
+                native_date_time_from_str("2024-01-01T00:00:00").and_utc(),
+                native_date_time_from_str("2024-02-01T00:00:00").and_utc()
+            ),
+            vec![0, 1]
+        );
+    }
+}
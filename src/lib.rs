@@ -0,0 +1,40 @@
+//! 予定の管理
+//!
+//! 予定の追加・削除・重複判定・期間での検索と、予定ファイルへの保存を行う。
+//! `calendar` コマンドはこのライブラリの上に作られている。
+//!
+//! ```
+//! use calendar::{Calendar, Schedule};
+//! use chrono::NaiveDate;
+//!
+//! let at = |hour| NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(hour, 0, 0).unwrap();
+//! let mut calendar = Calendar::default();
+//! let id = calendar
+//!     .add(Schedule::new(0, "定例".to_string(), at(9), at(10)))
+//!     .unwrap();
+//! assert_eq!(calendar.find(id).unwrap().subject, "定例");
+//! // 重なる予定は追加できない
+//! assert!(calendar.add(Schedule::new(0, "来客".to_string(), at(9), at(11))).is_err());
+//! ```
+
+pub mod attendee;
+pub mod calendar;
+pub mod clock;
+pub mod error;
+pub mod free;
+pub mod ics;
+mod index;
+pub mod location;
+pub mod recurrence;
+pub mod schedule;
+pub mod storage;
+pub mod tag;
+#[cfg(test)]
+pub(crate) mod test_util;
+pub mod view;
+pub mod zone;
+
+pub use calendar::{Calendar, Filter, ImportReport};
+pub use error::{CalendarError, Error};
+pub use schedule::{Availability, Occurrence, Override, Schedule, ScheduleId};
+pub use storage::{JsonStore, SqliteStore, Store};
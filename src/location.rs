@@ -0,0 +1,94 @@
+use std::{env, ffi::OsString, path::PathBuf};
+
+/// 予定ファイルのパスを指定する環境変数
+pub const FILE_ENV: &str = "CALENDAR_FILE";
+
+/// 予定ファイルの名前
+const FILE_NAME: &str = "schedules.json";
+
+/// 予定ファイルのパス
+///
+/// 次の順に最初に決まったものを使う。
+///
+/// 1. `--file` オプション
+/// 2. 環境変数 `CALENDAR_FILE`
+/// 3. `$XDG_DATA_HOME/calendar/schedules.json`
+/// 4. `$HOME/.local/share/calendar/schedules.json`
+/// 5. カレントディレクトリの `schedules.json`
+pub fn schedule_file(option: Option<PathBuf>) -> PathBuf {
+    resolve(
+        option,
+        env::var_os(FILE_ENV),
+        env::var_os("XDG_DATA_HOME"),
+        env::var_os("HOME"),
+    )
+}
+
+fn resolve(
+    option: Option<PathBuf>,
+    file: Option<OsString>,
+    xdg_data_home: Option<OsString>,
+    home: Option<OsString>,
+) -> PathBuf {
+    let non_empty = |value: Option<OsString>| value.filter(|value| !value.is_empty());
+    if let Some(path) = option.or_else(|| non_empty(file).map(PathBuf::from)) {
+        return path;
+    }
+    // XDG Base Directory の仕様どおり、相対パスの XDG_DATA_HOME は無視する
+    let data_home = non_empty(xdg_data_home)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| non_empty(home).map(|home| PathBuf::from(home).join(".local/share")));
+    match data_home {
+        Some(data_home) => data_home.join("calendar").join(FILE_NAME),
+        None => PathBuf::from(FILE_NAME),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(
+        Some("a.json"),
+        Some("b.json"),
+        Some("/data"),
+        Some("/home/me"),
+        "a.json"
+    )]
+    #[case(None, Some("b.json"), Some("/data"), Some("/home/me"), "b.json")]
+    #[case(
+        None,
+        None,
+        Some("/data"),
+        Some("/home/me"),
+        "/data/calendar/schedules.json"
+    )]
+    #[case(
+        None,
+        Some(""),
+        Some("data"),
+        Some("/home/me"),
+        "/home/me/.local/share/calendar/schedules.json"
+    )]
+    #[case(None, None, None, None, "schedules.json")]
+    fn test_resolve(
+        #[case] option: Option<&str>,
+        #[case] file: Option<&str>,
+        #[case] xdg_data_home: Option<&str>,
+        #[case] home: Option<&str>,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(
+            resolve(
+                option.map(PathBuf::from),
+                file.map(OsString::from),
+                xdg_data_home.map(OsString::from),
+                home.map(OsString::from),
+            ),
+            PathBuf::from(expected)
+        );
+    }
+}
@@ -1,209 +1,1193 @@
-use chrono::NaiveDateTime;
-use clap::{Parser, Subcommand};
-use serde::{Deserialize, Serialize};
+use calendar::{
+    attendee::Attendee,
+    clock::{Clock, SystemClock},
+    free, ics, location,
+    recurrence::Recurrence,
+    tag::{self, TagQuery},
+    view, zone, Availability, Calendar, CalendarError, Error, Filter, JsonStore, Schedule,
+    ScheduleId, SqliteStore, Store,
+};
+use chrono::{
+    DateTime, Datelike, Days, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday,
+};
+use chrono_tz::Tz;
+use clap::{Parser, Subcommand, ValueEnum};
 use std::{
-    fs::File,
-    io::{BufReader, BufWriter},
+    cell::Cell,
+    fs,
+    io::{self, IsTerminal},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
 };
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
-struct Schedule {
-    id: u64,
-    subject: String,
-    start: NaiveDateTime,
-    end: NaiveDateTime,
-}
-
-impl Schedule {
-    fn intersects(&self, other: &Schedule) -> bool {
-        self.start < other.end && other.start < self.end
-    }
-}
-
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
-struct Calendar {
-    schedules: Vec<Schedule>,
-}
+/// 一覧表示で繰り返し予定を展開する期間（今日か一覧の始まりの遅い方からの日数）
+const LIST_HORIZON_DAYS: i64 = 365;
 
-const SCHEDULE_FILE: &str = "schedules.json";
+/// 指定できる日付の年（前後の日や月を求めても日付の範囲を超えないようにする）
+const YEARS: RangeInclusive<i32> = 1..=9999;
 
 #[derive(Parser)]
+#[clap(after_help = "終了コード:
+  0  成功
+  2  引数が正しくない
+  3  ファイルの読み書きに失敗した（予定ファイルがない場合を含む）
+  4  予定ファイルを解釈できない
+  5  入力が正しくない
+  6  予定が重複している
+  7  指定した予定が見つからない")]
 struct Cli {
+    /// 予定ファイルのパス（省略時は環境変数 CALENDAR_FILE、$XDG_DATA_HOME/calendar/schedules.json、
+    /// ~/.local/share/calendar/schedules.json の順に使う）
+    #[clap(long, global = true)]
+    file: Option<PathBuf>,
+    /// 予定の保存先（`json:<パス>` または `sqlite:<パス>`、--file の代わりに指定する）
+    #[clap(long, global = true, value_parser = parse_store, conflicts_with = "file")]
+    store: Option<StoreSpec>,
     #[clap(subcommand)]
     command: Commands,
 }
 
 #[derive(Subcommand)]
 enum Commands {
+    /// 空の予定ファイルを作成する
+    Init {
+        /// 予定ファイルがすでにあっても空で上書きする
+        #[clap(long)]
+        force: bool,
+    },
     /// 予定の一覧表示
-    List,
+    List {
+        /// この日時（日付のみならその日の始まり）より後にかかる予定だけを表示する
+        #[clap(long, value_parser = parse_date_or_date_time)]
+        from: Option<DateOrDateTime>,
+        /// この日時（日付のみならその日の終わり）より前にかかる予定だけを表示する
+        #[clap(long, value_parser = parse_date_or_date_time)]
+        to: Option<DateOrDateTime>,
+        /// この日にかかる予定だけを表示する
+        #[clap(long, conflicts_with_all = ["from", "to"], value_parser = parse_date)]
+        on: Option<NaiveDate>,
+        /// タイトルにこの文字列を含む予定だけを表示する（大文字小文字は区別しない）
+        #[clap(long)]
+        contains: Option<String>,
+        /// 指定IDの予定だけを表示する（複数指定可）
+        #[clap(long = "id")]
+        ids: Vec<u64>,
+        /// タグが条件に合う予定だけを表示する（例: "work and not 1on1"、"private or (work and review)"。
+        /// 複数指定するとすべての条件に合うもの）
+        #[clap(long = "tag", value_parser = TagQuery::from_str)]
+        tags: Vec<TagQuery>,
+        /// 表示に使うタイムゾーン（例: "Europe/Berlin"、省略時は既定のタイムゾーン）
+        #[clap(long)]
+        tz: Option<Tz>,
+    },
     /// 予定の追加
     Add {
         /// タイトル
         subject: String,
-        /// 開始日時
-        start: NaiveDateTime,
-        /// 終了日時
-        end: NaiveDateTime,
+        /// 開始日時（YYYY-MM-DD のように日付だけにすると終日の予定）
+        #[clap(value_parser = parse_date_or_date_time)]
+        start: DateOrDateTime,
+        /// 終了日時（終日の予定なら最終日の日付で、省略するとその日だけ）
+        #[clap(value_parser = parse_date_or_date_time)]
+        end: Option<DateOrDateTime>,
+        /// 終日の予定を、重複の判定や空き時間の計算で時間を占めないものにする
+        #[clap(long)]
+        free: bool,
+        /// 繰り返しルール（例: "FREQ=WEEKLY;BYDAY=MO;COUNT=10"）
+        #[clap(long)]
+        rrule: Option<Recurrence>,
+        /// 開始・終了日時のタイムゾーン（例: "Asia/Tokyo"、省略時は既定のタイムゾーン）
+        #[clap(long)]
+        tz: Option<Tz>,
+        /// 重複する場合は最も近い空いている時間にずらして追加する
+        #[clap(long)]
+        auto_shift: bool,
+        /// 付けるタグ（複数指定可）
+        #[clap(long = "tag", value_parser = tag::normalize)]
+        tags: Vec<String>,
+        /// 場所
+        #[clap(long)]
+        location: Option<String>,
+        /// 説明（改行を含めてよい）
+        #[clap(long)]
+        description: Option<String>,
+        /// 参加者（例: "山田太郎 <taro@example.com>:accepted"、返事は省略すると未回答。複数指定可）
+        #[clap(long = "attendee")]
+        attendees: Vec<Attendee>,
+    },
+    /// 予定の詳細を表示する
+    Show {
+        /// 表示する予定のID
+        id: u64,
+        /// 表示に使うタイムゾーン（省略時は既定のタイムゾーン）
+        #[clap(long)]
+        tz: Option<Tz>,
+    },
+    /// 予定の削除
+    Delete {
+        /// 削除する予定のID（複数指定可）
+        #[clap(required = true)]
+        ids: Vec<u64>,
+    },
+    /// 予定の編集
+    Edit {
+        /// 編集する予定のID
+        id: u64,
+        /// 新しいタイトル
+        #[clap(long)]
+        subject: Option<String>,
+        /// 新しい開始日時（終日の予定なら日付）
+        #[clap(long, value_parser = parse_date_or_date_time)]
+        start: Option<DateOrDateTime>,
+        /// 新しい終了日時（終日の予定なら最終日の日付）
+        #[clap(long, value_parser = parse_date_or_date_time)]
+        end: Option<DateOrDateTime>,
+        /// 終日の予定を、重複の判定や空き時間の計算で時間を占めるものにする
+        #[clap(long, conflicts_with = "free")]
+        busy: bool,
+        /// 終日の予定を、重複の判定や空き時間の計算で時間を占めないものにする
+        #[clap(long)]
+        free: bool,
+        /// 新しいタイムゾーン（開始・終了日時はこのタイムゾーンの時刻として扱う）
+        #[clap(long)]
+        tz: Option<Tz>,
+        /// 新しい場所（空文字列で消す）
+        #[clap(long)]
+        location: Option<String>,
+        /// 新しい説明（空文字列で消す）
+        #[clap(long)]
+        description: Option<String>,
+        /// 参加者を加える（同じメールアドレスの参加者がいれば置き換えて返事を更新する。複数指定可）
+        #[clap(long = "attendee")]
+        attendees: Vec<Attendee>,
+        /// メールアドレスで参加者を外す（複数指定可）
+        #[clap(long = "remove-attendee")]
+        removed_attendees: Vec<String>,
+    },
+    /// 繰り返し予定の1回分を取り消す
+    Exclude {
+        /// 繰り返し予定のID
+        id: u64,
+        /// 取り消す回の開始日時（予定のタイムゾーンでの時刻）
+        occurrence: NaiveDateTime,
+    },
+    /// 繰り返し予定の1回分だけを変更する
+    Override {
+        /// 繰り返し予定のID
+        id: u64,
+        /// 変更する回の（元の）開始日時（予定のタイムゾーンでの時刻）
+        occurrence: NaiveDateTime,
+        /// 新しいタイトル
+        #[clap(long)]
+        subject: Option<String>,
+        /// 新しい開始日時
+        #[clap(long)]
+        start: Option<NaiveDateTime>,
+        /// 新しい終了日時
+        #[clap(long)]
+        end: Option<NaiveDateTime>,
+    },
+    /// 予定にタグを付ける
+    Tag {
+        /// タグを付ける予定のID
+        id: u64,
+        /// 付けるタグ
+        #[clap(required = true, value_parser = tag::normalize)]
+        tags: Vec<String>,
+    },
+    /// 予定からタグを外す
+    Untag {
+        /// タグを外す予定のID
+        id: u64,
+        /// 外すタグ
+        #[clap(required = true, value_parser = tag::normalize)]
+        tags: Vec<String>,
+    },
+    /// 使われているタグと、そのタグが付いた予定の数を表示する
+    Tags,
+    /// iCalendar (.ics) ファイルから予定を取り込む
+    Import {
+        /// 取り込むファイル
+        // グローバルな --file と引数名が重なると予定ファイルのパスとして扱われるので input とする
+        #[clap(value_name = "FILE")]
+        input: PathBuf,
+    },
+    /// 予定を書き出す
+    Export {
+        /// 出力形式
+        #[clap(long, value_enum, default_value_t = ExportFormat::Ics)]
+        format: ExportFormat,
+        /// 出力先ファイル（省略時は標準出力）
+        #[clap(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// 月のカレンダーを表示する
+    Month {
+        /// 表示する年月（YYYY-MM、省略時は今月）
+        #[clap(value_parser = parse_year_month)]
+        month: Option<NaiveDate>,
+        /// 日曜日始まりで表示する
+        #[clap(long)]
+        sunday_first: bool,
+        /// 表示に使うタイムゾーン（省略時は既定のタイムゾーン）
+        #[clap(long)]
+        tz: Option<Tz>,
+    },
+    /// 週の予定を1時間ごとの表で表示する
+    Week {
+        /// 表示する週に含まれる日（省略時は今日）
+        #[clap(long, value_parser = parse_date)]
+        date: Option<NaiveDate>,
+        /// 表示を始める時刻（時）
+        #[clap(long, default_value_t = 8, value_parser = clap::value_parser!(u32).range(0..24))]
+        from_hour: u32,
+        /// 表示を終える時刻（時、この時刻の行は含まない）
+        #[clap(long, default_value_t = 20, value_parser = clap::value_parser!(u32).range(1..=24))]
+        to_hour: u32,
+        /// 日曜日始まりで表示する
+        #[clap(long)]
+        sunday_first: bool,
+        /// 表示に使うタイムゾーン（省略時は既定のタイムゾーン）
+        #[clap(long)]
+        tz: Option<Tz>,
+    },
+    /// 今日の予定を表示する
+    Today {
+        /// 表示に使うタイムゾーン（省略時は既定のタイムゾーン）
+        #[clap(long)]
+        tz: Option<Tz>,
+    },
+    /// 明日の予定を表示する
+    Tomorrow {
+        /// 表示に使うタイムゾーン（省略時は既定のタイムゾーン）
+        #[clap(long)]
+        tz: Option<Tz>,
+    },
+    /// 今日から指定日数分の予定を日ごとに表示する
+    Agenda {
+        /// 表示する日数
+        #[clap(default_value_t = 7, value_parser = clap::value_parser!(u64).range(1..=3660))]
+        days: u64,
+        /// 表示に使うタイムゾーン（省略時は既定のタイムゾーン）
+        #[clap(long)]
+        tz: Option<Tz>,
     },
+    /// 予定の入っていない時間を表示する
+    Free {
+        /// 探す期間の始まり（日付のみならその日の始まり）
+        #[clap(long, value_parser = parse_date_or_date_time)]
+        from: DateOrDateTime,
+        /// 探す期間の終わり（日付のみならその日の終わり）
+        #[clap(long, value_parser = parse_date_or_date_time)]
+        to: DateOrDateTime,
+        /// 必要な長さ（例: "30m"、"1h30m"）
+        #[clap(long, default_value = "30m", value_parser = parse_length)]
+        min: Duration,
+        /// 各日のこの時間帯の中だけを探す（例: "09:00-18:00"）
+        #[clap(long, value_parser = parse_time_range)]
+        within: Option<(NaiveTime, NaiveTime)>,
+        /// 土日を除く
+        #[clap(long)]
+        skip_weekends: bool,
+        /// 日時の解釈と表示に使うタイムゾーン（省略時は既定のタイムゾーン）
+        #[clap(long)]
+        tz: Option<Tz>,
+    },
+}
+
+/// `YYYY-MM` 形式の年月をその月の1日として解釈する
+fn parse_year_month(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&format!("{}-01", s), "%Y-%m-%d")
+        .map_err(|_| format!("年月は YYYY-MM 形式で指定してください: {}", s))
+        .and_then(|month| check_year(month, s))
+}
+
+fn parse_date(s: &str) -> Result<NaiveDate, String> {
+    s.parse()
+        .map_err(|_| format!("日付は YYYY-MM-DD 形式で指定してください: {}", s))
+        .and_then(|date| check_year(date, s))
+}
+
+fn check_year(date: NaiveDate, s: &str) -> Result<NaiveDate, String> {
+    if YEARS.contains(&date.year()) {
+        Ok(date)
+    } else {
+        Err(format!(
+            "日付は {} 年から {} 年までで指定してください: {}",
+            YEARS.start(),
+            YEARS.end(),
+            s
+        ))
+    }
+}
+
+/// 日付のみ、または日時の指定
+#[derive(Debug, Clone, Copy)]
+enum DateOrDateTime {
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+}
+
+impl DateOrDateTime {
+    /// 期間の始まりとしての絶対時刻（日付のみならその日の始まり）
+    fn as_start(self, tz: Tz) -> DateTime<Utc> {
+        match self {
+            DateOrDateTime::Date(date) => zone::start_of_day(tz, date),
+            DateOrDateTime::DateTime(local) => zone::resolve_local(tz, local).with_timezone(&Utc),
+        }
+    }
+
+    /// 期間の終わりとしての絶対時刻（日付のみならその日の終わり）
+    fn as_end(self, tz: Tz) -> DateTime<Utc> {
+        match self {
+            DateOrDateTime::Date(date) => zone::start_of_day(tz, date + Days::new(1)),
+            DateOrDateTime::DateTime(local) => zone::resolve_local(tz, local).with_timezone(&Utc),
+        }
+    }
+
+    /// 予定の開始日時としての時刻（終日の予定は日付のみ、それ以外は日時で指定する）
+    fn local_start(self, all_day: bool) -> Result<NaiveDateTime, CalendarError> {
+        self.local(all_day, 0)
+    }
+
+    /// 予定の終了日時としての時刻（終日の予定なら最終日の翌日の始まり）
+    fn local_end(self, all_day: bool) -> Result<NaiveDateTime, CalendarError> {
+        self.local(all_day, 1)
+    }
+
+    fn local(self, all_day: bool, days: u64) -> Result<NaiveDateTime, CalendarError> {
+        let message = match (self, all_day) {
+            (DateOrDateTime::Date(date), true) => {
+                return Ok((date + Days::new(days)).and_time(NaiveTime::MIN))
+            }
+            (DateOrDateTime::DateTime(local), false) => return Ok(local),
+            (DateOrDateTime::Date(_), false) => {
+                "時刻のある予定の日時は YYYY-MM-DDTHH:MM:SS 形式で指定してください"
+            }
+            (DateOrDateTime::DateTime(_), true) => {
+                "終日の予定の日付は YYYY-MM-DD 形式で指定してください"
+            }
+        };
+        Err(CalendarError::Invalid(vec![message.to_string()]))
+    }
+}
+
+/// `YYYY-MM-DD` または `YYYY-MM-DDTHH:MM:SS` 形式の日付・日時を解釈する
+fn parse_date_or_date_time(s: &str) -> Result<DateOrDateTime, String> {
+    if let Ok(date_time) = s.parse::<NaiveDateTime>() {
+        return check_year(date_time.date(), s).map(|_| DateOrDateTime::DateTime(date_time));
+    }
+    let date = s.parse().map_err(|_| {
+        format!(
+            "日付は YYYY-MM-DD、日時は YYYY-MM-DDTHH:MM:SS 形式で指定してください: {}",
+            s
+        )
+    })?;
+    check_year(date, s).map(DateOrDateTime::Date)
+}
+
+/// `1h30m`、`45m`、`2d` のような長さを解釈する
+fn parse_length(s: &str) -> Result<Duration, String> {
+    let error = || {
+        format!(
+            "長さは 1h30m のように日(d)・時間(h)・分(m)で指定してください: {}",
+            s
+        )
+    };
+    let mut length = Duration::zero();
+    let mut number = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let n: i64 = number.parse().map_err(|_| error())?;
+        number.clear();
+        let part = match c {
+            'd' => Duration::try_days(n),
+            'h' => Duration::try_hours(n),
+            'm' => Duration::try_minutes(n),
+            _ => None,
+        };
+        length = part
+            .and_then(|part| length.checked_add(&part))
+            .ok_or_else(error)?;
+    }
+    if !number.is_empty() || length <= Duration::zero() {
+        return Err(error());
+    }
+    Ok(length)
+}
+
+/// `09:00-18:00` 形式の時間帯を解釈する
+fn parse_time_range(s: &str) -> Result<(NaiveTime, NaiveTime), String> {
+    let error = || format!("時間帯は 09:00-18:00 の形式で指定してください: {}", s);
+    let (start, end) = s.split_once('-').ok_or_else(error)?;
+    let start = NaiveTime::parse_from_str(start, "%H:%M").map_err(|_| error())?;
+    let end = NaiveTime::parse_from_str(end, "%H:%M").map_err(|_| error())?;
+    if start >= end {
+        return Err(format!("時間帯の終わりは始まりより後にしてください: {}", s));
+    }
+    Ok((start, end))
+}
+
+/// `--store` で指定した保存先
+#[derive(Debug, Clone, PartialEq)]
+enum StoreSpec {
+    Json(PathBuf),
+    Sqlite(PathBuf),
+}
+
+fn parse_store(s: &str) -> Result<StoreSpec, String> {
+    match s.split_once(':') {
+        Some(("json", path)) if !path.is_empty() => Ok(StoreSpec::Json(path.into())),
+        Some(("sqlite", path)) if !path.is_empty() => Ok(StoreSpec::Sqlite(path.into())),
+        _ => Err("json:<パス> または sqlite:<パス> の形式で指定してください".to_string()),
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    /// iCalendar (RFC 5545)
+    Ics,
+    /// このツールの JSON 形式
+    Json,
 }
 
 fn main() {
-    let options = Cli::parse();
+    if let Err(error) = run(Cli::parse()) {
+        eprintln!("エラー：{}", error);
+        process::exit(error.exit_code());
+    }
+}
+
+/// 読み込んだ予定に正しくないものがあれば、最初に読み込んだときに警告する保存先
+///
+/// 正しくない予定があっても、edit で直したり delete で削除したりできるように読み込みは続ける。
+struct Warned {
+    store: Box<dyn Store>,
+    warned: Cell<bool>,
+}
+
+impl Warned {
+    fn new(store: Box<dyn Store>) -> Self {
+        Warned {
+            store,
+            warned: Cell::new(false),
+        }
+    }
+
+    fn warn(&self, calendar: Calendar) -> Result<Calendar, Error> {
+        if !self.warned.replace(true) {
+            let problems = calendar.validate();
+            if !problems.is_empty() {
+                eprintln!(
+                    "警告：{} に正しくない予定があります（edit で直すか delete で削除してください）",
+                    self.path().display()
+                );
+                for (id, problem) in problems {
+                    eprintln!("  ID {}: {}", id, problem);
+                }
+            }
+        }
+        Ok(calendar)
+    }
+}
+
+impl Store for Warned {
+    fn path(&self) -> &Path {
+        self.store.path()
+    }
+
+    fn load(&self) -> Result<Calendar, Error> {
+        self.warn(self.store.load()?)
+    }
+
+    fn save(&self, calendar: &Calendar) -> Result<(), Error> {
+        self.store.save(calendar)
+    }
+
+    fn load_for_change(
+        &self,
+        ids: &[ScheduleId],
+        range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    ) -> Result<Calendar, Error> {
+        self.warn(self.store.load_for_change(ids, range)?)
+    }
+
+    fn insert(&self, calendar: &Calendar, ids: &[ScheduleId]) -> Result<(), Error> {
+        self.store.insert(calendar, ids)
+    }
+
+    fn update(&self, calendar: &Calendar, id: ScheduleId) -> Result<(), Error> {
+        self.store.update(calendar, id)
+    }
+
+    fn delete(&self, calendar: &Calendar, ids: &[ScheduleId]) -> Result<(), Error> {
+        self.store.delete(calendar, ids)
+    }
+
+    fn query(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Calendar, Error> {
+        self.warn(self.store.query(from, to)?)
+    }
+}
+
+fn run(options: Cli) -> Result<(), Error> {
+    let clock = SystemClock;
+    let store: Box<dyn Store> = match options.store {
+        Some(StoreSpec::Json(path)) => Box::new(JsonStore::new(path)),
+        Some(StoreSpec::Sqlite(path)) => Box::new(SqliteStore::new(path)),
+        None => Box::new(JsonStore::new(location::schedule_file(options.file))),
+    };
+    let store = Warned::new(store);
+    let path = store.path().to_path_buf();
+    // 読んでから保存するまでの間に他のプロセスが書き込まないよう、書き込むコマンドは終わるまでロックを持つ
+    let writes = matches!(
+        options.command,
+        Commands::Init { .. }
+            | Commands::Add { .. }
+            | Commands::Delete { .. }
+            | Commands::Edit { .. }
+            | Commands::Exclude { .. }
+            | Commands::Override { .. }
+            | Commands::Tag { .. }
+            | Commands::Untag { .. }
+            | Commands::Import { .. }
+    );
+    let _lock = writes.then(|| store.lock()).transpose()?;
     match options.command {
-        Commands::List => {
-            let calender = read_calender();
-            show_list(&calender);
+        Commands::Init { force } => {
+            if path.exists() && !force {
+                return Err(Error::FileExists(path));
+            }
+            store.save(&Calendar::default())?;
+            println!("予定ファイル {} を作成しました", path.display());
+        }
+        Commands::List {
+            from,
+            to,
+            on,
+            contains,
+            ids,
+            tags,
+            tz,
+        } => {
+            let tz = tz.unwrap_or_else(zone::default_tz);
+            let (from, to) = match on {
+                Some(date) => (
+                    Some(zone::start_of_day(tz, date)),
+                    Some(zone::start_of_day(tz, date + Days::new(1))),
+                ),
+                None => (
+                    from.map(|from| from.as_start(tz)),
+                    to.map(|to| to.as_end(tz)),
+                ),
+            };
+            let filter = Filter {
+                from,
+                to,
+                contains,
+                ids,
+                tags,
+            };
+            // 期間と重なりうる予定だけを読み込む
+            let calender = store.query(
+                from.unwrap_or(DateTime::<Utc>::MIN_UTC),
+                to.unwrap_or(DateTime::<Utc>::MAX_UTC),
+            )?;
+            show_list(&calender, &filter, list_until(from, clock.now()), tz);
         }
         Commands::Add {
             subject,
             start,
             end,
+            free,
+            rrule,
+            tz,
+            auto_shift,
+            tags,
+            location,
+            description,
+            attendees,
+        } => {
+            let tz = tz.unwrap_or_else(zone::default_tz);
+            // 開始を日付だけで指定したら終日の予定
+            let all_day = matches!(start, DateOrDateTime::Date(_));
+            let schedule = if all_day {
+                Schedule {
+                    all_day: Some(if free {
+                        Availability::Free
+                    } else {
+                        Availability::Busy
+                    }),
+                    ..Schedule::new(
+                        0,
+                        subject,
+                        start.local_start(true)?,
+                        end.unwrap_or(start).local_end(true)?,
+                    )
+                }
+            } else if free {
+                return Err(CalendarError::Invalid(vec![
+                    "--free は終日の予定にだけ指定できます".to_string()
+                ])
+                .into());
+            } else {
+                let end = end.ok_or_else(|| {
+                    CalendarError::Invalid(vec!["終了日時を指定してください".to_string()])
+                })?;
+                Schedule::new(0, subject, start.local_start(false)?, end.local_end(false)?)
+            };
+            let mut candidate = Schedule {
+                tz: Some(tz),
+                recurrence: rrule,
+                location: location.filter(|location| !location.is_empty()),
+                description: description.filter(|description| !description.is_empty()),
+                ..schedule
+            };
+            for tag in tags {
+                if !candidate.tags.contains(&tag) {
+                    candidate.tags.push(tag);
+                }
+            }
+            for attendee in attendees {
+                candidate.add_attendee(attendee);
+            }
+            let range = (
+                candidate.first_start(),
+                candidate.last_end().unwrap_or(DateTime::<Utc>::MAX_UTC),
+            );
+            let mut calender = store.load_for_change(&[], Some(range))?;
+            let error = match calender.add(candidate.clone()) {
+                Ok(id) => {
+                    store.insert(&calender, &[id])?;
+                    println!("予定を追加しました");
+                    return Ok(());
+                }
+                Err(error @ CalendarError::Conflict(_)) => error,
+                Err(error) => return Err(error.into()),
+            };
+
+            let show = |schedule: &Schedule| view::format_schedule(schedule, tz);
+            println!("重複している予定：");
+            // 繰り返し予定は重なっている回を示す
+            for (conflict, occurrence) in calender.conflicts(&candidate) {
+                println!(
+                    "  ID {}  {}  {}",
+                    conflict.id,
+                    occurrence.subject,
+                    view::format_occurrence(conflict, &occurrence, tz)
+                );
+            }
+            // ずらした先の重複も確かめるので全体を読み込む
+            let mut calender = store.load()?;
+            let suggestions = calender.suggest_slots(&candidate);
+            match suggestions.first() {
+                Some(slot) if auto_shift => {
+                    let id = calender.add(slot.clone())?;
+                    store.insert(&calender, &[id])?;
+                    println!("{} に移して予定を追加しました", show(slot));
+                    return Ok(());
+                }
+                Some(_) => {
+                    println!("空いている時間の候補：");
+                    for slot in &suggestions {
+                        println!("  {}", show(slot));
+                    }
+                }
+                None => println!("前後に空いている時間が見つかりませんでした"),
+            }
+            return Err(error.into());
+        }
+        Commands::Show { id, tz } => {
+            let calender = store.load()?;
+            let schedule = calender.find(id).ok_or(CalendarError::NotFound(id))?;
+            print!(
+                "{}",
+                view::render_detail(schedule, tz.unwrap_or_else(zone::default_tz))
+            );
+        }
+        Commands::Delete { ids } => {
+            let mut calender = store.load_for_change(&ids, None)?;
+            // 見つからないIDがあれば何も削除せず、すべて報告する
+            let removed = match calender.remove_all(&ids) {
+                Ok(removed) => removed,
+                Err(mut errors) => {
+                    let last = errors.pop().unwrap();
+                    for error in errors {
+                        eprintln!("エラー：{}", error);
+                    }
+                    return Err(last.into());
+                }
+            };
+            let ids: Vec<ScheduleId> = removed.iter().map(|schedule| schedule.id).collect();
+            store.delete(&calender, &ids)?;
+            println!("予定を削除しました");
+        }
+        Commands::Edit {
+            id,
+            subject,
+            start,
+            end,
+            busy,
+            free,
+            tz,
+            location,
+            description,
+            attendees,
+            removed_attendees,
+        } => {
+            change_schedule(&store, id, |calender| {
+                let all_day = calender
+                    .find(id)
+                    .ok_or(CalendarError::NotFound(id))?
+                    .is_all_day();
+                let start = start.map(|start| start.local_start(all_day)).transpose()?;
+                let end = end.map(|end| end.local_end(all_day)).transpose()?;
+                // 時間を占めなくするなら先に、占めるようにするなら日時を変えてから重複を確かめる
+                if free {
+                    calender.set_availability(id, Availability::Free)?;
+                }
+                // 日時が変わらなければ重複判定は要らない
+                if subject.is_some() || start.is_some() || end.is_some() || tz.is_some() {
+                    calender.edit(id, subject.clone(), start, end, tz)?;
+                }
+                if busy {
+                    calender.set_availability(id, Availability::Busy)?;
+                }
+                calender.edit_details(
+                    id,
+                    location.clone(),
+                    description.clone(),
+                    attendees.clone(),
+                    &removed_attendees,
+                )
+            })?;
+            println!("予定を更新しました");
+        }
+        Commands::Exclude { id, occurrence } => {
+            let mut calender = store.load_for_change(&[id], None)?;
+            calender.exclude(id, occurrence)?;
+            store.update(&calender, id)?;
+            println!("予定を取り消しました");
+        }
+        Commands::Override {
+            id,
+            occurrence,
+            subject,
+            start,
+            end,
+        } => {
+            change_schedule(&store, id, |calender| {
+                calender.override_occurrence(id, occurrence, subject.clone(), start, end)
+            })?;
+            println!("予定を更新しました");
+        }
+        Commands::Tag { id, tags } => {
+            let mut calender = store.load_for_change(&[id], None)?;
+            calender.tag(id, &tags)?;
+            store.update(&calender, id)?;
+            println!("タグを付けました");
+        }
+        Commands::Untag { id, tags } => {
+            let mut calender = store.load_for_change(&[id], None)?;
+            calender.untag(id, &tags)?;
+            store.update(&calender, id)?;
+            println!("タグを外しました");
+        }
+        Commands::Tags => {
+            let calender = store.load()?;
+            for (tag, count) in calender.tags() {
+                println!("{}\t{}", tag, count);
+            }
+        }
+        Commands::Import { input } => {
+            let mut calender = store.load()?;
+            let input = fs::read_to_string(&input).map_err(Error::io(&input))?;
+            let mut schedules = Vec::new();
+            for result in ics::parse(&input, zone::default_tz()) {
+                match result {
+                    Ok(schedule) => schedules.push(schedule),
+                    Err(invalid) => println!("スキップ（解釈できません）：{}", invalid),
+                }
+            }
+            let report = calender.import(schedules);
+            for schedule in &report.duplicates {
+                println!(
+                    "スキップ（取り込み済み）：{} ({})",
+                    schedule.subject, schedule.start
+                );
+            }
+            for (schedule, problems) in &report.invalid {
+                println!(
+                    "スキップ（{}）：{} ({})",
+                    problems.join("、"),
+                    schedule.subject,
+                    schedule.start
+                );
+            }
+            for (schedule, id) in &report.conflicts {
+                println!(
+                    "スキップ（ID {} の予定と重複）：{} ({})",
+                    id, schedule.subject, schedule.start
+                );
+            }
+            // 取り込んだ予定だけをまとめて書き込む
+            if !report.imported.is_empty() {
+                store.insert(&calender, &report.imported)?;
+            }
+            println!("{} 件の予定を取り込みました", report.imported.len());
+        }
+        Commands::Export { format, output } => {
+            let calender = store.load()?;
+            let exported = match format {
+                ExportFormat::Ics => ics::write(&calender, clock.now()),
+                ExportFormat::Json => serde_json::to_string_pretty(&calender).unwrap(),
+            };
+            match output {
+                Some(output) => fs::write(&output, exported).map_err(Error::io(&output))?,
+                None => print!("{}", exported),
+            }
+        }
+        Commands::Month {
+            month,
+            sunday_first,
+            tz,
+        } => {
+            let tz = tz.unwrap_or_else(zone::default_tz);
+            let today = clock.now().with_timezone(&tz).date_naive();
+            let month = month.unwrap_or(today);
+            let first = month.with_day(1).unwrap();
+            let calender = store.query(
+                zone::start_of_day(tz, first),
+                zone::start_of_day(tz, first + Months::new(1)),
+            )?;
+            let week_start = if sunday_first {
+                Weekday::Sun
+            } else {
+                Weekday::Mon
+            };
+            print!(
+                "{}",
+                view::render_month(
+                    &calender,
+                    month,
+                    today,
+                    tz,
+                    week_start,
+                    io::stdout().is_terminal(),
+                )
+            );
+        }
+        Commands::Week {
+            date,
+            from_hour,
+            to_hour,
+            sunday_first,
+            tz,
         } => {
-            let mut calender = read_calender();
-            if add_schedule(&mut calender, subject, start, end) {
-                save_calender(&calender);
-                println!("予定を追加しました");
+            if from_hour >= to_hour {
+                return Err(Error::Validation(
+                    "表示を終える時刻は始める時刻より後にしてください".to_string(),
+                ));
+            }
+            let tz = tz.unwrap_or_else(zone::default_tz);
+            let date = date.unwrap_or_else(|| clock.now().with_timezone(&tz).date_naive());
+            let week_start = if sunday_first {
+                Weekday::Sun
             } else {
-                println!("エラー：予定が重複しています");
+                Weekday::Mon
+            };
+            let first = date - Days::new(date.weekday().days_since(week_start).into());
+            let calender = store.query(
+                zone::start_of_day(tz, first),
+                zone::start_of_day(tz, first + Days::new(7)),
+            )?;
+            print!(
+                "{}",
+                view::render_week(&calender, date, tz, week_start, from_hour..to_hour)
+            );
+        }
+        Commands::Free {
+            from,
+            to,
+            min,
+            within,
+            skip_weekends,
+            tz,
+        } => {
+            let tz = tz.unwrap_or_else(zone::default_tz);
+            let query = free::FreeQuery {
+                from: from.as_start(tz),
+                to: to.as_end(tz),
+                min,
+                within,
+                skip_weekends,
+                tz,
+            };
+            let calender = store.query(query.from, query.to)?;
+            let slots = free::free_slots(&calender, &query);
+            if slots.is_empty() {
+                println!("空き時間はありません");
+            }
+            for (start, end) in slots {
+                println!(
+                    "{}  ({})",
+                    view::format_span(
+                        start.with_timezone(&tz).naive_local(),
+                        end.with_timezone(&tz).naive_local()
+                    ),
+                    view::format_duration(end - start)
+                );
             }
         }
+        Commands::Today { tz } => show_agenda(&store, &clock, tz, 0, 1)?,
+        Commands::Tomorrow { tz } => show_agenda(&store, &clock, tz, 1, 1)?,
+        Commands::Agenda { days, tz } => show_agenda(&store, &clock, tz, 0, days)?,
     }
+    Ok(())
 }
 
-fn read_calender() -> Calendar {
-    let file = File::open(SCHEDULE_FILE).unwrap();
-    let reader = BufReader::new(file);
-    serde_json::from_reader(reader).unwrap()
+/// 今日から `offset` 日後を初日として `days` 日分の予定を表示する
+fn show_agenda(
+    store: &dyn Store,
+    clock: &impl Clock,
+    tz: Option<Tz>,
+    offset: u64,
+    days: u64,
+) -> Result<(), Error> {
+    let tz = tz.unwrap_or_else(zone::default_tz);
+    let first = clock.now().with_timezone(&tz).date_naive() + Days::new(offset);
+    let calender = store.query(
+        zone::start_of_day(tz, first),
+        zone::start_of_day(tz, first + Days::new(days)),
+    )?;
+    print!(
+        "{}",
+        view::render_agenda(&calender, clock, offset, days, tz)
+    );
+    Ok(())
 }
 
-fn save_calender(calendar: &Calendar) {
-    let file = File::create(SCHEDULE_FILE).unwrap();
-    let writer = BufWriter::new(file);
-    serde_json::to_writer(writer, calendar).unwrap();
+/// 予定 `id` を `change` で変更して保存する
+///
+/// 重複判定には変更後の予定と重なりうる予定があれば足りるので、まず予定だけを読み込んで
+/// 変更後の期間を求め、その期間の予定を読み込み直してからもう一度変更する。
+fn change_schedule(
+    store: &dyn Store,
+    id: ScheduleId,
+    change: impl Fn(&mut Calendar) -> Result<(), CalendarError>,
+) -> Result<(), Error> {
+    let mut calender = store.load_for_change(&[id], None)?;
+    change(&mut calender)?;
+    let edited = changed(&calender, id);
+    let range = (
+        edited.first_start(),
+        edited.last_end().unwrap_or(DateTime::<Utc>::MAX_UTC),
+    );
+    let mut calender = store.load_for_change(&[id], Some(range))?;
+    change(&mut calender)?;
+    store.update(&calender, id)
 }
 
-fn show_list(calendar: &Calendar) {
-    // 予定の表示
-    println!("ID\tStart\tEnd\tSubject");
-    for schedule in &calendar.schedules {
-        println!(
-            "{}\t{}\t{}\t{}",
-            schedule.id, schedule.start, schedule.end, schedule.subject
-        );
-    }
+/// 追加・変更したばかりの予定
+fn changed(calendar: &Calendar, id: ScheduleId) -> &Schedule {
+    calendar
+        .find(id)
+        .expect("追加・変更した予定はカレンダーにある")
 }
 
-fn add_schedule(
-    calendar: &mut Calendar,
-    subject: String,
-    start: NaiveDateTime,
-    end: NaiveDateTime,
-) -> bool {
-    // 予定の作成
-    let id = calendar.schedules.len() as u64;
-    let new_schedule = Schedule {
-        id,
-        subject,
-        start,
-        end,
-    };
+/// 一覧表示で終わりのない繰り返し予定を展開する終わりの日時
+fn list_until(from: Option<DateTime<Utc>>, now: DateTime<Utc>) -> DateTime<Utc> {
+    from.map_or(now, |from| from.max(now)) + Duration::days(LIST_HORIZON_DAYS)
+}
 
-    // 予定の重複判定
-    for schedule in &calendar.schedules {
-        if schedule.intersects(&new_schedule) {
-            println!("エラー：予定が重複しています");
-            return false;
-        }
+fn show_list(calendar: &Calendar, filter: &Filter, until: DateTime<Utc>, tz: Tz) {
+    // 予定の表示（終日の予定を先に日付だけで、それ以外は tz の時刻で表示する）
+    println!("ID\tStart\tEnd\tSubject\tTags");
+    let (all_day, occurrences): (Vec<_>, Vec<_>) = calendar
+        .filter(filter, until)
+        .into_iter()
+        .partition(|(schedule, _)| schedule.is_all_day());
+    for (schedule, occurrence) in all_day {
+        let (first, last) = view::all_day_dates(&occurrence);
+        println!(
+            "{}\t{}\t{}\t{}\t{}",
+            schedule.id,
+            first,
+            last,
+            occurrence.subject,
+            schedule.tags.join(",")
+        );
+    }
+    for (schedule, occurrence) in occurrences {
+        println!(
+            "{}\t{}\t{}\t{}\t{}",
+            schedule.id,
+            occurrence.start.with_timezone(&tz).naive_local(),
+            occurrence.end.with_timezone(&tz).naive_local(),
+            occurrence.subject,
+            schedule.tags.join(",")
+        );
     }
-
-    // 予定の追加
-    calendar.schedules.push(new_schedule);
-    true
 }
 
 #[cfg(test)]
 mod tests {
-    use std::vec;
-
     use super::*;
     use rstest::rstest;
 
-    fn native_date_time(
-        year: i32,
-        month: u32,
-        day: u32,
-        hour: u32,
-        minute: u32,
-        second: u32,
-    ) -> NaiveDateTime {
-        chrono::NaiveDate::from_ymd_opt(year, month, day)
-            .unwrap()
-            .and_hms_opt(hour, minute, second)
-            .unwrap()
+    #[test]
+    fn test_import_file_is_not_calendar_file() {
+        let options = Cli::try_parse_from(["calendar", "import", "x.ics"]).unwrap();
+        assert_eq!(options.file, None);
+        assert!(matches!(
+            options.command,
+            Commands::Import { input } if input == std::path::Path::new("x.ics")
+        ));
+
+        let options =
+            Cli::try_parse_from(["calendar", "import", "x.ics", "--file", "s.json"]).unwrap();
+        assert_eq!(options.file, Some(PathBuf::from("s.json")));
     }
 
-    #[rstest]
-    #[case(18, 15, 18, 45, false)]
-    #[case(18, 15, 19, 45, true)]
-    #[case(18, 15, 20, 45, true)]
-    #[case(19, 15, 19, 45, true)]
-    #[case(19, 15, 20, 45, true)]
-    #[case(20, 15, 20, 45, false)]
-    fn test_schedule_intersects(
-        #[case] h0: u32,
-        #[case] m0: u32,
-        #[case] h1: u32,
-        #[case] m1: u32,
-        #[case] should_intersect: bool,
-    ) {
-        let schedule = Schedule {
-            id: 1,
-            subject: "既存予定".to_string(),
-            start: native_date_time(2024, 1, 1, h0, m0, 0),
-            end: native_date_time(2024, 1, 1, h1, m1, 0),
+    #[test]
+    fn test_init_and_missing_file() {
+        let dir = std::env::temp_dir().join(format!("calendar-test-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("schedules.json");
+        let run_with = |args: &[&str]| {
+            let mut argv = vec!["calendar", "--file", path.to_str().unwrap()];
+            argv.extend(args);
+            run(Cli::try_parse_from(argv).unwrap())
         };
-        let new_schedule = Schedule {
-            id: 999,
-            subject: "新規予定".to_string(),
-            start: native_date_time(2024, 1, 1, 19, 0, 0),
-            end: native_date_time(2024, 1, 1, 20, 0, 0),
+
+        // ファイルがなければ作らずに、init を促すエラーにする
+        assert!(matches!(run_with(&["list"]), Err(Error::FileNotFound(_))));
+        assert!(matches!(
+            run_with(&["add", "休暇", "2024-01-01"]),
+            Err(Error::FileNotFound(_))
+        ));
+        assert!(!path.exists());
+
+        // 空のファイルは予定のないカレンダーとして読む
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, "").unwrap();
+        assert!(run_with(&["list"]).is_ok());
+
+        // 既にあるファイルは --force なしでは上書きしない
+        assert!(matches!(run_with(&["init"]), Err(Error::FileExists(_))));
+        run_with(&["add", "休暇", "2024-01-01"]).unwrap();
+        run_with(&["init", "--force"]).unwrap();
+        assert_eq!(JsonStore::new(&path).load().unwrap(), Calendar::default());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    #[case(&["edit", "1", "--end", "2024-01-02T10:00:00"], vec![0, 1])]
+    #[case(&["delete", "1"], vec![0])]
+    fn test_repair_invalid_schedule(#[case] repair: &[&str], #[case] expected: Vec<ScheduleId>) {
+        let dir = std::env::temp_dir().join(format!("calendar-test-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("schedules.json");
+        let run_with = |args: &[&str]| {
+            let mut argv = vec!["calendar", "--file", path.to_str().unwrap()];
+            argv.extend(args);
+            run(Cli::try_parse_from(argv).unwrap())
         };
-        assert_eq!(schedule.intersects(&new_schedule), should_intersect);
+        // ID 1 は終了日時が開始日時より前の壊れた予定
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            &path,
+            r#"{"schedules":[
+                {"id":0,"subject":"定例","start":"2024-01-01T09:00:00","end":"2024-01-01T10:00:00","tz":"UTC"},
+                {"id":1,"subject":"来客","start":"2024-01-02T09:00:00","end":"2024-01-02T08:00:00","tz":"UTC"}
+            ],"next_id":2}"#,
+        )
+        .unwrap();
+
+        // 壊れた予定があっても読み込めるが、新たに正しくない予定は作れない
+        assert!(run_with(&["list"]).is_ok());
+        assert!(matches!(
+            run_with(&["add", "面談", "2024-01-03T10:00:00", "2024-01-03T09:00:00"]),
+            Err(Error::Calendar(CalendarError::Invalid(_)))
+        ));
+
+        run_with(repair).unwrap();
+        let calendar = JsonStore::new(&path).load().unwrap();
+        assert_eq!(calendar.validate(), vec![]);
+        let ids: Vec<ScheduleId> = calendar.schedules().iter().map(|s| s.id).collect();
+        assert_eq!(ids, expected);
+        fs::remove_dir_all(&dir).unwrap();
     }
 
-    #[test]
-    fn test_add_schedule() {
-        let mut calendar = Calendar {
-            schedules: vec![Schedule {
-                id: 0,
-                subject: "テスト予定".to_string(),
-                start: native_date_time(2024, 11, 19, 11, 22, 33),
-                end: native_date_time(2024, 11, 19, 22, 33, 44),
+    #[rstest]
+    #[case("2024-01-01", true)]
+    #[case("9999-12-31", true)]
+    #[case("+262142-12-31", false)]
+    #[case("0000-01-01", false)]
+    fn test_parse_date_range(#[case] input: &str, #[case] ok: bool) {
+        assert_eq!(parse_date(input).is_ok(), ok);
+        assert_eq!(parse_date_or_date_time(input).is_ok(), ok);
+        let date_time = format!("{}T00:00:00", input);
+        assert_eq!(parse_date_or_date_time(&date_time).is_ok(), ok);
+    }
+
+    #[rstest]
+    #[case(None, 731)]
+    #[case(Some("2024-06-01"), 579)]
+    #[case(Some("2030-01-01"), 365)]
+    fn test_list_expands_from_later_start(#[case] from: Option<&str>, #[case] expected: usize) {
+        let now = zone::start_of_day(Tz::UTC, "2025-01-01".parse().unwrap());
+        let from = from.map(|from| zone::start_of_day(Tz::UTC, from.parse().unwrap()));
+        let calendar = Calendar::new(
+            vec![Schedule {
+                recurrence: Some("FREQ=DAILY".parse().unwrap()),
+                ..Schedule::new(
+                    0,
+                    "朝会".to_string(),
+                    "2024-01-01T09:00:00".parse().unwrap(),
+                    "2024-01-01T09:30:00".parse().unwrap(),
+                )
             }],
-        };
-        add_schedule(
-            &mut calendar,
-            "テスト予定2".to_string(),
-            native_date_time(2023, 12, 8, 9, 0, 0),
-            native_date_time(2023, 12, 8, 10, 0, 0),
+            1,
         );
-        let expected = Calendar {
-            schedules: vec![
-                Schedule {
-                    id: 0,
-                    subject: "テスト予定".to_string(),
-                    start: native_date_time(2024, 11, 19, 11, 22, 33),
-                    end: native_date_time(2024, 11, 19, 22, 33, 44),
-                },
-                Schedule {
-                    id: 1,
-                    subject: "テスト予定2".to_string(),
-                    start: native_date_time(2023, 12, 8, 9, 0, 0),
-                    end: native_date_time(2023, 12, 8, 10, 0, 0),
-                },
-            ],
+        let filter = Filter {
+            from,
+            ..Filter::default()
         };
-        assert_eq!(calendar, expected);
+        // 今日から1年より先が一覧の始まりなら、そこから1年分を展開する
+        assert_eq!(
+            calendar.filter(&filter, list_until(from, now)).len(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_agenda_days_are_bounded() {
+        assert!(Cli::try_parse_from(["calendar", "agenda", "3660"]).is_ok());
+        assert!(Cli::try_parse_from(["calendar", "agenda", "999999999999"]).is_err());
+    }
+
+    #[rstest]
+    #[case("30m", Some(Duration::minutes(30)))]
+    #[case("1h30m", Some(Duration::minutes(90)))]
+    #[case("1d", Some(Duration::days(1)))]
+    #[case("0m", None)]
+    #[case("30", None)]
+    #[case("1x", None)]
+    #[case("999999999999999d", None)]
+    #[case("9223372036854775807m9223372036854775807m", None)]
+    fn test_parse_length(#[case] input: &str, #[case] expected: Option<Duration>) {
+        assert_eq!(parse_length(input).ok(), expected);
+    }
+
+    #[rstest]
+    #[case("sqlite:calendar.db", Ok(StoreSpec::Sqlite("calendar.db".into())))]
+    #[case("json:/tmp/schedules.json", Ok(StoreSpec::Json("/tmp/schedules.json".into())))]
+    #[case("sqlite:", Err(()))]
+    #[case("calendar.db", Err(()))]
+    fn test_parse_store(#[case] input: &str, #[case] expected: Result<StoreSpec, ()>) {
+        assert_eq!(parse_store(input).map_err(|_| ()), expected);
     }
 }
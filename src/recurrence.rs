@@ -0,0 +1,682 @@
+use crate::zone;
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// 発生日が1つも見つからない期間がこれだけ続いたら展開を打ち切る
+const MAX_EMPTY_PERIODS: u32 = 10_000;
+
+/// INTERVAL の上限
+const MAX_INTERVAL: u32 = 1_000;
+
+/// COUNT の上限
+const MAX_COUNT: u32 = 10_000;
+
+/// 繰り返しの頻度 (FREQ)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// 曜日指定 (BYDAY)。`2TU` のように序数付きで「第2火曜日」を表せる
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeekdayNum {
+    pub ordinal: Option<i32>,
+    pub weekday: Weekday,
+}
+
+/// 繰り返しの終わり (UNTIL)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Until {
+    /// 日付のみ（その日の終わりまでを含める）
+    Date(NaiveDate),
+    /// 予定のタイムゾーンでの日時
+    Local(NaiveDateTime),
+    /// UTC の日時（`Z` 付き）
+    Utc(NaiveDateTime),
+}
+
+/// RFC 5545 の RRULE のうち FREQ, INTERVAL, BYDAY, BYMONTHDAY, COUNT, UNTIL に対応した繰り返しルール
+///
+/// JSON には `FREQ=WEEKLY;BYDAY=MO` のような RRULE 文字列として保存する。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Recurrence {
+    pub freq: Frequency,
+    pub interval: u32,
+    pub by_day: Vec<WeekdayNum>,
+    pub by_month_day: Vec<i32>,
+    pub count: Option<u32>,
+    pub until: Option<Until>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRecurrenceError(String);
+
+impl fmt::Display for ParseRecurrenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "繰り返しルールが不正です: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseRecurrenceError {}
+
+impl Recurrence {
+    /// `dtstart` を初回とする発生日時（`tz` での開始日時）を昇順に返す
+    ///
+    /// COUNT も UNTIL もないルールでは無限に続くため、呼び出し側で打ち切ること。
+    pub fn starts(&self, dtstart: NaiveDateTime, tz: Tz) -> Starts<'_> {
+        Starts {
+            rule: self,
+            dtstart,
+            tz,
+            until: self.until.map(|until| until.instant(tz)),
+            period: 0,
+            pending: Vec::new(),
+            emitted: 0,
+            empty_periods: 0,
+            finished: false,
+        }
+    }
+
+    /// 期間番号 `period` に含まれる発生日の候補を昇順で返す
+    ///
+    /// 期間の先頭が日付の表現範囲を超える場合は `None` を返す。
+    fn candidates(&self, dtstart: NaiveDate, period: u32) -> Option<Vec<NaiveDate>> {
+        let step = period.saturating_mul(self.interval);
+        let mut dates = match self.freq {
+            Frequency::Daily => {
+                let date = dtstart.checked_add_days(Days::new(step.into()))?;
+                vec![date]
+                    .into_iter()
+                    .filter(|date| self.matches_weekday(*date) && self.matches_month_day(*date))
+                    .collect()
+            }
+            Frequency::Weekly => {
+                let week_start = dtstart
+                    .checked_sub_days(Days::new(dtstart.weekday().num_days_from_monday().into()))?
+                    .checked_add_days(Days::new(u64::from(step) * 7))?;
+                let weekdays: Vec<Weekday> = if self.by_day.is_empty() {
+                    vec![dtstart.weekday()]
+                } else {
+                    self.by_day.iter().map(|day| day.weekday).collect()
+                };
+                weekdays
+                    .into_iter()
+                    .filter_map(|weekday| {
+                        week_start
+                            .checked_add_days(Days::new(weekday.num_days_from_monday().into()))
+                    })
+                    .filter(|date| self.matches_month_day(*date))
+                    .collect()
+            }
+            Frequency::Monthly => {
+                let first = first_of_month(dtstart).checked_add_months(Months::new(step))?;
+                self.month_candidates(dtstart, first)
+            }
+            Frequency::Yearly => {
+                let year = i32::try_from(step)
+                    .ok()
+                    .and_then(|step| dtstart.year().checked_add(step))?;
+                let first = NaiveDate::from_ymd_opt(year, 1, 1)?;
+                let last = NaiveDate::from_ymd_opt(first.year(), 12, 31).unwrap();
+                if !self.by_month_day.is_empty() {
+                    (0..12)
+                        .filter_map(|month| first.checked_add_months(Months::new(month)))
+                        .flat_map(|first| self.month_days(first))
+                        .filter(|date| self.matches_weekday(*date))
+                        .collect()
+                } else if !self.by_day.is_empty() {
+                    expand_weekdays(first, last, &self.by_day)
+                } else {
+                    NaiveDate::from_ymd_opt(first.year(), dtstart.month(), dtstart.day())
+                        .into_iter()
+                        .collect()
+                }
+            }
+        };
+        dates.sort();
+        dates.dedup();
+        Some(dates)
+    }
+
+    /// 月単位の期間 (`first` はその月の1日) の候補日
+    fn month_candidates(&self, dtstart: NaiveDate, first: NaiveDate) -> Vec<NaiveDate> {
+        if !self.by_month_day.is_empty() {
+            self.month_days(first)
+                .into_iter()
+                .filter(|date| self.matches_weekday(*date))
+                .collect()
+        } else if !self.by_day.is_empty() {
+            expand_weekdays(first, last_of_month(first), &self.by_day)
+        } else {
+            first.with_day(dtstart.day()).into_iter().collect()
+        }
+    }
+
+    /// BYMONTHDAY を `first` の月の日付に解決する（負数は月末から数える）
+    fn month_days(&self, first: NaiveDate) -> Vec<NaiveDate> {
+        let last_day = last_of_month(first).day() as i32;
+        self.by_month_day
+            .iter()
+            .filter_map(|&day| {
+                let day = if day < 0 { last_day + day + 1 } else { day };
+                (1..=last_day)
+                    .contains(&day)
+                    .then(|| first.with_day(day as u32).unwrap())
+            })
+            .collect()
+    }
+
+    fn matches_weekday(&self, date: NaiveDate) -> bool {
+        self.by_day.is_empty() || self.by_day.iter().any(|day| day.weekday == date.weekday())
+    }
+
+    fn matches_month_day(&self, date: NaiveDate) -> bool {
+        self.by_month_day.is_empty() || self.month_days(first_of_month(date)).contains(&date)
+    }
+
+    fn validate(&self) -> Result<(), ParseRecurrenceError> {
+        if !(1..=MAX_INTERVAL).contains(&self.interval) {
+            return Err(ParseRecurrenceError(format!(
+                "INTERVAL は1以上{}以下を指定してください",
+                MAX_INTERVAL
+            )));
+        }
+        if self
+            .count
+            .is_some_and(|count| !(1..=MAX_COUNT).contains(&count))
+        {
+            return Err(ParseRecurrenceError(format!(
+                "COUNT は1以上{}以下を指定してください",
+                MAX_COUNT
+            )));
+        }
+        if self.count.is_some() && self.until.is_some() {
+            return Err(ParseRecurrenceError(
+                "COUNT と UNTIL は同時に指定できません".into(),
+            ));
+        }
+        if let Some(day) = self
+            .by_month_day
+            .iter()
+            .find(|day| !(1..=31).contains(&day.abs()))
+        {
+            return Err(ParseRecurrenceError(format!("BYMONTHDAY={}", day)));
+        }
+        let max_ordinal = match self.freq {
+            Frequency::Monthly => 5,
+            Frequency::Yearly => 53,
+            Frequency::Daily | Frequency::Weekly => 0,
+        };
+        for day in &self.by_day {
+            if let Some(ordinal) = day.ordinal {
+                if ordinal == 0 || ordinal.abs() > max_ordinal {
+                    return Err(ParseRecurrenceError(format!(
+                        "BYDAY={} はこの FREQ では指定できません",
+                        day
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// [`Recurrence::starts`] が返す発生日時のイテレータ
+pub struct Starts<'a> {
+    rule: &'a Recurrence,
+    dtstart: NaiveDateTime,
+    tz: Tz,
+    /// UNTIL の絶対時刻
+    until: Option<DateTime<Utc>>,
+    period: u32,
+    /// 現在の期間で未出力の候補（降順に積んで末尾から取り出す）
+    pending: Vec<NaiveDateTime>,
+    emitted: u32,
+    empty_periods: u32,
+    finished: bool,
+}
+
+impl Iterator for Starts<'_> {
+    type Item = NaiveDateTime;
+
+    fn next(&mut self) -> Option<NaiveDateTime> {
+        if self.finished || self.rule.count.is_some_and(|count| self.emitted >= count) {
+            return None;
+        }
+        // DTSTART はルールに合致しなくても必ず初回として扱う
+        if self.emitted == 0 && self.period == 0 && self.pending.is_empty() {
+            self.fill_period();
+            self.pending.retain(|start| *start != self.dtstart);
+            return self.emit(self.dtstart);
+        }
+        while self.pending.is_empty() {
+            if self.finished || self.empty_periods >= MAX_EMPTY_PERIODS {
+                self.finished = true;
+                return None;
+            }
+            self.fill_period();
+        }
+        let start = self.pending.pop().unwrap();
+        self.emit(start)
+    }
+}
+
+impl Starts<'_> {
+    fn fill_period(&mut self) {
+        let time: NaiveTime = self.dtstart.time();
+        // 日付の表現範囲を超えたらそれ以降の発生日はない
+        let Some(candidates) = self.rule.candidates(self.dtstart.date(), self.period) else {
+            self.finished = true;
+            return;
+        };
+        let mut starts: Vec<NaiveDateTime> = candidates
+            .into_iter()
+            .map(|date| date.and_time(time))
+            .filter(|start| *start > self.dtstart)
+            .collect();
+        starts.reverse();
+        if starts.is_empty() {
+            self.empty_periods += 1;
+        } else {
+            self.empty_periods = 0;
+        }
+        self.pending = starts;
+        self.period += 1;
+    }
+
+    fn emit(&mut self, start: NaiveDateTime) -> Option<NaiveDateTime> {
+        if self
+            .until
+            .is_some_and(|until| zone::resolve_local(self.tz, start) > until)
+        {
+            self.finished = true;
+            return None;
+        }
+        self.emitted += 1;
+        Some(start)
+    }
+}
+
+/// `first` から `last` までの日付のうち BYDAY に該当するもの（序数は期間内で数える）
+fn expand_weekdays(first: NaiveDate, last: NaiveDate, by_day: &[WeekdayNum]) -> Vec<NaiveDate> {
+    let mut dates = Vec::new();
+    for day in by_day {
+        let matching: Vec<NaiveDate> = first
+            .iter_days()
+            .take_while(|date| *date <= last)
+            .filter(|date| date.weekday() == day.weekday)
+            .collect();
+        match day.ordinal {
+            None => dates.extend(matching),
+            Some(n) if n > 0 => dates.extend(matching.get(n as usize - 1)),
+            Some(n) => dates.extend(
+                matching
+                    .len()
+                    .checked_sub(n.unsigned_abs() as usize)
+                    .map(|index| matching[index]),
+            ),
+        }
+    }
+    dates.sort();
+    dates
+}
+
+fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap()
+}
+
+fn last_of_month(date: NaiveDate) -> NaiveDate {
+    first_of_month(date)
+        .checked_add_months(Months::new(1))
+        .unwrap()
+        .pred_opt()
+        .unwrap()
+}
+
+fn parse_weekday(s: &str) -> Option<Weekday> {
+    match s {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+impl Until {
+    /// `tz` の予定での繰り返しの終わりの絶対時刻
+    pub fn instant(self, tz: Tz) -> DateTime<Utc> {
+        match self {
+            Until::Date(date) => {
+                zone::resolve_local(tz, date.and_hms_opt(23, 59, 59).unwrap()).with_timezone(&Utc)
+            }
+            Until::Local(local) => zone::resolve_local(tz, local).with_timezone(&Utc),
+            Until::Utc(utc) => utc.and_utc(),
+        }
+    }
+}
+
+impl FromStr for Until {
+    type Err = ParseRecurrenceError;
+
+    /// `20240131`, `20240131T090000`, `20240131T090000Z` 形式
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseRecurrenceError(format!("UNTIL={}", s));
+        if s.len() == 8 {
+            NaiveDate::parse_from_str(s, "%Y%m%d")
+                .map(Until::Date)
+                .map_err(|_| error())
+        } else if let Some(utc) = s.strip_suffix('Z') {
+            NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+                .map(Until::Utc)
+                .map_err(|_| error())
+        } else {
+            NaiveDateTime::parse_from_str(s, "%Y%m%dT%H%M%S")
+                .map(Until::Local)
+                .map_err(|_| error())
+        }
+    }
+}
+
+impl fmt::Display for Until {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Until::Date(date) => write!(f, "{}", date.format("%Y%m%d")),
+            Until::Local(local) => write!(f, "{}", local.format("%Y%m%dT%H%M%S")),
+            Until::Utc(utc) => write!(f, "{}Z", utc.format("%Y%m%dT%H%M%S")),
+        }
+    }
+}
+
+impl FromStr for WeekdayNum {
+    type Err = ParseRecurrenceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseRecurrenceError(format!("BYDAY={}", s));
+        let split = s.len().checked_sub(2).ok_or_else(error)?;
+        let (ordinal, weekday) = s.split_at(split);
+        let weekday = parse_weekday(weekday).ok_or_else(error)?;
+        let ordinal = match ordinal {
+            "" => None,
+            ordinal => Some(
+                ordinal
+                    .trim_start_matches('+')
+                    .parse()
+                    .map_err(|_| error())?,
+            ),
+        };
+        Ok(WeekdayNum { ordinal, weekday })
+    }
+}
+
+impl fmt::Display for WeekdayNum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ordinal) = self.ordinal {
+            write!(f, "{}", ordinal)?;
+        }
+        f.write_str(weekday_code(self.weekday))
+    }
+}
+
+impl FromStr for Frequency {
+    type Err = ParseRecurrenceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "DAILY" => Ok(Frequency::Daily),
+            "WEEKLY" => Ok(Frequency::Weekly),
+            "MONTHLY" => Ok(Frequency::Monthly),
+            "YEARLY" => Ok(Frequency::Yearly),
+            _ => Err(ParseRecurrenceError(format!(
+                "FREQ={} には対応していません",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        })
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = ParseRecurrenceError;
+
+    /// `FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;COUNT=10` 形式（`RRULE:` 接頭辞は省略可）
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s.strip_prefix("RRULE:").unwrap_or(s);
+        let mut freq = None;
+        let mut rule = Recurrence {
+            freq: Frequency::Daily,
+            interval: 1,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            count: None,
+            until: None,
+        };
+        for part in s.split(';').filter(|part| !part.is_empty()) {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| ParseRecurrenceError(part.to_string()))?;
+            let error = || ParseRecurrenceError(part.to_string());
+            match name.to_ascii_uppercase().as_str() {
+                "FREQ" => freq = Some(value.to_ascii_uppercase().parse()?),
+                "INTERVAL" => rule.interval = value.parse().map_err(|_| error())?,
+                "COUNT" => rule.count = Some(value.parse().map_err(|_| error())?),
+                "UNTIL" => rule.until = Some(value.to_ascii_uppercase().parse()?),
+                "BYDAY" => {
+                    rule.by_day = value
+                        .split(',')
+                        .map(|day| day.to_ascii_uppercase().parse())
+                        .collect::<Result<_, _>>()?
+                }
+                "BYMONTHDAY" => {
+                    rule.by_month_day = value
+                        .split(',')
+                        .map(|day| day.parse().map_err(|_| error()))
+                        .collect::<Result<_, _>>()?
+                }
+                // 週の始まりは月曜日固定で扱う
+                "WKST" if value.eq_ignore_ascii_case("MO") => {}
+                _ => {
+                    return Err(ParseRecurrenceError(format!(
+                        "{} には対応していません",
+                        part
+                    )))
+                }
+            }
+        }
+        rule.freq = freq.ok_or_else(|| ParseRecurrenceError("FREQ がありません".into()))?;
+        rule.validate()?;
+        Ok(rule)
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FREQ={}", self.freq)?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<String> = self.by_day.iter().map(ToString::to_string).collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if !self.by_month_day.is_empty() {
+            let days: Vec<String> = self.by_month_day.iter().map(ToString::to_string).collect();
+            write!(f, ";BYMONTHDAY={}", days.join(","))?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for Recurrence {
+    type Error = ParseRecurrenceError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Recurrence> for String {
+    fn from(rule: Recurrence) -> Self {
+        rule.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::native_date_time_from_str;
+    use rstest::rstest;
+
+    fn starts(rule: &str, dtstart: &str, limit: usize) -> Vec<String> {
+        let rule: Recurrence = rule.parse().unwrap();
+        let dtstart = NaiveDateTime::parse_from_str(dtstart, "%Y-%m-%dT%H:%M:%S").unwrap();
+        rule.starts(dtstart, Tz::UTC)
+            .take(limit)
+            .map(|start| start.format("%Y-%m-%d %H:%M").to_string())
+            .collect()
+    }
+
+    #[rstest]
+    #[case(
+        "FREQ=DAILY;INTERVAL=2;COUNT=3",
+        "2024-01-01T09:00:00",
+        vec!["2024-01-01 09:00", "2024-01-03 09:00", "2024-01-05 09:00"]
+    )]
+    #[case(
+        "FREQ=WEEKLY;BYDAY=MO,WE",
+        "2024-01-01T09:00:00",
+        vec!["2024-01-01 09:00", "2024-01-03 09:00", "2024-01-08 09:00", "2024-01-10 09:00"]
+    )]
+    #[case(
+        "FREQ=WEEKLY;INTERVAL=2;UNTIL=20240115",
+        "2024-01-01T09:00:00",
+        vec!["2024-01-01 09:00", "2024-01-15 09:00"]
+    )]
+    #[case(
+        "FREQ=MONTHLY;BYMONTHDAY=31",
+        "2024-01-31T10:00:00",
+        vec!["2024-01-31 10:00", "2024-03-31 10:00", "2024-05-31 10:00", "2024-07-31 10:00"]
+    )]
+    #[case(
+        "FREQ=MONTHLY;BYDAY=2TU;COUNT=3",
+        "2024-01-09T10:00:00",
+        vec!["2024-01-09 10:00", "2024-02-13 10:00", "2024-03-12 10:00"]
+    )]
+    #[case(
+        "FREQ=MONTHLY;BYDAY=-1FR",
+        "2024-01-26T18:00:00",
+        vec!["2024-01-26 18:00", "2024-02-23 18:00", "2024-03-29 18:00", "2024-04-26 18:00"]
+    )]
+    #[case(
+        "FREQ=YEARLY",
+        "2024-02-29T00:00:00",
+        vec!["2024-02-29 00:00", "2028-02-29 00:00", "2032-02-29 00:00", "2036-02-29 00:00"]
+    )]
+    #[case(
+        "FREQ=DAILY;BYDAY=SA,SU;COUNT=3",
+        "2024-01-06T08:00:00",
+        vec!["2024-01-06 08:00", "2024-01-07 08:00", "2024-01-13 08:00"]
+    )]
+    fn test_recurrence_starts(
+        #[case] rule: &str,
+        #[case] dtstart: &str,
+        #[case] expected: Vec<&str>,
+    ) {
+        assert_eq!(starts(rule, dtstart, 4), expected);
+    }
+
+    #[rstest]
+    #[case("FREQ=DAILY;INTERVAL=1000")]
+    #[case("FREQ=WEEKLY;INTERVAL=1000;BYDAY=MO,SU")]
+    #[case("FREQ=MONTHLY;INTERVAL=1000")]
+    #[case("FREQ=YEARLY;INTERVAL=1000")]
+    fn test_recurrence_stops_at_date_limit(#[case] rule: &str) {
+        // 日付の表現範囲を超える発生日は作らずに打ち切る
+        let rule: Recurrence = rule.parse().unwrap();
+        let dtstart = NaiveDate::MAX.and_time(NaiveTime::MIN);
+        assert_eq!(
+            rule.starts(dtstart, Tz::UTC).collect::<Vec<_>>(),
+            vec![dtstart]
+        );
+    }
+
+    #[rstest]
+    // UTC の UNTIL は絶対時刻で比べる（東京の 12:00 は UTC の 3:00）
+    #[case("FREQ=DAILY;UNTIL=20240105T030000Z", "2024-01-05 12:00")]
+    #[case("FREQ=DAILY;UNTIL=20240105T025959Z", "2024-01-04 12:00")]
+    // Z のない UNTIL は予定のタイムゾーンの日時
+    #[case("FREQ=DAILY;UNTIL=20240105T120000", "2024-01-05 12:00")]
+    #[case("FREQ=DAILY;UNTIL=20240105T030000", "2024-01-04 12:00")]
+    #[case("FREQ=DAILY;UNTIL=20240105", "2024-01-05 12:00")]
+    fn test_recurrence_until_in_time_zone(#[case] rule: &str, #[case] last: &str) {
+        let rule: Recurrence = rule.parse().unwrap();
+        let dtstart = native_date_time_from_str("2024-01-01T12:00:00");
+        let starts: Vec<NaiveDateTime> = rule.starts(dtstart, chrono_tz::Asia::Tokyo).collect();
+        assert_eq!(
+            starts.last().unwrap().format("%Y-%m-%d %H:%M").to_string(),
+            last
+        );
+    }
+
+    #[rstest]
+    #[case("FREQ=WEEKLY;BYDAY=MO,WE;COUNT=10")]
+    #[case("FREQ=MONTHLY;INTERVAL=2;BYDAY=1MO,-1FR")]
+    #[case("FREQ=YEARLY;BYMONTHDAY=1,-1;UNTIL=20301231T235959")]
+    #[case("FREQ=DAILY;UNTIL=20240105T030000Z")]
+    #[case("FREQ=WEEKLY;UNTIL=20240131")]
+    fn test_recurrence_round_trip(#[case] rule: &str) {
+        assert_eq!(rule.parse::<Recurrence>().unwrap().to_string(), rule);
+    }
+
+    #[rstest]
+    #[case("BYDAY=MO")]
+    #[case("FREQ=HOURLY")]
+    #[case("FREQ=DAILY;INTERVAL=0")]
+    #[case("FREQ=WEEKLY;INTERVAL=4000000000")]
+    #[case("FREQ=DAILY;COUNT=4000000000")]
+    #[case("FREQ=DAILY;COUNT=3;UNTIL=20240101")]
+    #[case("FREQ=WEEKLY;BYDAY=2MO")]
+    #[case("FREQ=MONTHLY;BYMONTHDAY=32")]
+    #[case("FREQ=DAILY;BYSETPOS=1")]
+    fn test_recurrence_parse_error(#[case] rule: &str) {
+        assert!(rule.parse::<Recurrence>().is_err());
+    }
+}
@@ -0,0 +1,623 @@
+use crate::{attendee::Attendee, recurrence::Recurrence, zone};
+use chrono::{DateTime, Days, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::{iter, mem};
+use uuid::Uuid;
+
+/// 予定のID
+pub type ScheduleId = u64;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// 予定（繰り返しの場合は1つの系列）
+pub struct Schedule {
+    pub id: ScheduleId,
+    /// ファイル間の取り込み・書き出しでも変わらない一意な識別子
+    ///
+    /// これを持たない古いファイルでは、読み込み時に [`Schedule::legacy_uid`] で補う。
+    #[serde(default)]
+    pub uid: String,
+    pub subject: String,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+    /// 開始・終了日時を解釈するタイムゾーン（古いファイルでは読み込み時に既定のタイムゾーンを補う）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tz: Option<Tz>,
+    /// 繰り返しルール（単発の予定なら None）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<Recurrence>,
+    /// 繰り返しから除外した発生の開始日時 (EXDATE)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exdates: Vec<NaiveDateTime>,
+    /// 1回分だけ変更した発生
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<Override>,
+    /// 分類のためのタグ（小文字）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// 場所
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// 説明（複数行可）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// 参加者
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attendees: Vec<Attendee>,
+    /// 終日の予定なら、その間の時間を占めるかどうか（時刻のある予定なら None）
+    ///
+    /// 終日の予定の開始・終了日時はどちらも 0 時で、終了日時の日付は含まない。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub all_day: Option<Availability>,
+}
+
+/// 終日の予定が重複判定や空き時間の計算でその間の時間を占めるかどうか
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Availability {
+    /// 占める（出張など）
+    Busy,
+    /// 占めない（締め切りの目印など）
+    Free,
+}
+
+/// 繰り返し予定のうち1回分だけを変更した内容 (RECURRENCE-ID)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Override {
+    /// 変更前の発生の開始日時
+    pub recurrence_id: NaiveDateTime,
+    /// 変更後のタイトル（None なら元の予定と同じ）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+}
+
+/// 繰り返しを展開した1回分の予定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Occurrence<'a> {
+    pub subject: &'a str,
+    pub start: DateTime<Tz>,
+    pub end: DateTime<Tz>,
+    /// 繰り返し予定の場合、変更前の発生の開始日時
+    pub recurrence_id: Option<NaiveDateTime>,
+}
+
+/// 終わりのない繰り返し予定同士の重複判定で、展開を打ち切るまでの日数
+const CONFLICT_HORIZON_DAYS: i64 = 366 * 2;
+
+/// 1回分の予定として認める最長の長さ（日数）
+const MAX_DURATION_DAYS: i64 = 31;
+
+pub(crate) fn generate_uid() -> String {
+    Uuid::new_v4().to_string()
+}
+
+impl Schedule {
+    /// UID を持たない古い予定の UID（読み込むたびに変わらないよう ID と開始日時から決める）
+    pub fn legacy_uid(&self) -> String {
+        format!(
+            "{}-{}@calendar",
+            self.id,
+            self.start.format("%Y%m%dT%H%M%S")
+        )
+    }
+
+    pub fn new(id: ScheduleId, subject: String, start: NaiveDateTime, end: NaiveDateTime) -> Self {
+        Schedule {
+            id,
+            uid: generate_uid(),
+            subject,
+            start,
+            end,
+            tz: None,
+            recurrence: None,
+            exdates: Vec::new(),
+            overrides: Vec::new(),
+            tags: Vec::new(),
+            location: None,
+            description: None,
+            attendees: Vec::new(),
+            all_day: None,
+        }
+    }
+
+    /// `first` から `last` まで（`last` を含む）の終日の予定を作る
+    pub fn new_all_day(
+        id: ScheduleId,
+        subject: String,
+        first: NaiveDate,
+        last: NaiveDate,
+        availability: Availability,
+    ) -> Self {
+        Schedule {
+            all_day: Some(availability),
+            ..Schedule::new(
+                id,
+                subject,
+                first.and_time(NaiveTime::MIN),
+                (last + Days::new(1)).and_time(NaiveTime::MIN),
+            )
+        }
+    }
+
+    /// 終日の予定かどうか
+    pub fn is_all_day(&self) -> bool {
+        self.all_day.is_some()
+    }
+
+    /// 重複判定や空き時間の計算で時間を占めるかどうか（時刻のある予定は常に占める）
+    pub fn is_busy(&self) -> bool {
+        self.all_day != Some(Availability::Free)
+    }
+
+    /// 参加者を加える
+    ///
+    /// 同じメールアドレスの参加者がいれば置き換える（返事の更新）。名前が空なら元の名前を残す。
+    pub fn add_attendee(&mut self, mut attendee: Attendee) {
+        match self.attendees.iter_mut().find(|a| a.is(&attendee.email)) {
+            Some(existing) => {
+                if attendee.name.is_empty() {
+                    attendee.name = mem::take(&mut existing.name);
+                }
+                *existing = attendee;
+            }
+            None => self.attendees.push(attendee),
+        }
+    }
+
+    /// 予定のタイムゾーン
+    pub fn tz(&self) -> Tz {
+        self.tz.unwrap_or(Tz::UTC)
+    }
+
+    /// 除外・変更されていない発生を開始日時の昇順で返す（繰り返しがなければ1回のみ）
+    pub fn regular_occurrences(&self) -> Box<dyn Iterator<Item = Occurrence<'_>> + '_> {
+        let tz = self.tz();
+        let duration = self.end - self.start;
+        match &self.recurrence {
+            None => Box::new(iter::once(Occurrence {
+                subject: &self.subject,
+                start: zone::resolve_local(tz, self.start),
+                end: zone::resolve_local(tz, self.end),
+                recurrence_id: None,
+            })),
+            Some(rule) => Box::new(
+                rule.starts(self.start, tz)
+                    .filter(|start| {
+                        !self.exdates.contains(start)
+                            && !self.overrides.iter().any(|o| o.recurrence_id == *start)
+                    })
+                    .map(move |start| Occurrence {
+                        subject: &self.subject,
+                        start: zone::resolve_local(tz, start),
+                        end: zone::resolve_local(tz, start + duration),
+                        recurrence_id: Some(start),
+                    }),
+            ),
+        }
+    }
+
+    /// 個別に変更された発生を返す
+    pub fn overridden_occurrences(&self) -> impl Iterator<Item = Occurrence<'_>> + '_ {
+        let tz = self.tz();
+        self.overrides.iter().map(move |o| Occurrence {
+            subject: o.subject.as_deref().unwrap_or(&self.subject),
+            start: zone::resolve_local(tz, o.start),
+            end: zone::resolve_local(tz, o.end),
+            recurrence_id: Some(o.recurrence_id),
+        })
+    }
+
+    /// `[from, to)` と重なる発生を開始日時の昇順で返す
+    pub fn occurrences_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Vec<Occurrence<'_>> {
+        let mut occurrences: Vec<Occurrence> = self
+            .regular_occurrences()
+            .take_while(|occurrence| occurrence.start < to)
+            .chain(self.overridden_occurrences())
+            .filter(|occurrence| occurrence.start < to && occurrence.end > from)
+            .collect();
+        occurrences.sort_by_key(|occurrence| (occurrence.start, occurrence.end));
+        occurrences
+    }
+
+    /// 予定として正しくない点をすべて返す
+    pub fn problems(&self) -> Vec<String> {
+        let tz = self.tz();
+        let mut problems = Vec::new();
+        if self.subject.trim().is_empty() {
+            problems.push("タイトルが空です".to_string());
+        }
+        problems.extend(span_problem(tz, self.start, self.end));
+        if self.is_all_day() && !is_midnight(self.start, self.end) {
+            problems.push("終日の予定の開始・終了日時が 0 時ではありません".to_string());
+        }
+        for exdate in &self.exdates {
+            if !self.generates(*exdate) {
+                problems.push(format!(
+                    "取り消した {} 開始の回は繰り返しにありません",
+                    exdate
+                ));
+            }
+        }
+        for o in &self.overrides {
+            if !self.generates(o.recurrence_id) {
+                problems.push(format!(
+                    "変更した {} 開始の回は繰り返しにありません",
+                    o.recurrence_id
+                ));
+            }
+            if o.subject
+                .as_ref()
+                .is_some_and(|subject| subject.trim().is_empty())
+            {
+                problems.push(format!("{} 開始の回のタイトルが空です", o.recurrence_id));
+            }
+            if let Some(problem) = span_problem(tz, o.start, o.end) {
+                problems.push(format!("{} 開始の回の{}", o.recurrence_id, problem));
+            }
+            if self.is_all_day() && !is_midnight(o.start, o.end) {
+                problems.push(format!(
+                    "{} 開始の回の開始・終了日時が 0 時ではありません",
+                    o.recurrence_id
+                ));
+            }
+        }
+        problems
+    }
+
+    /// 最初の発生の開始日時
+    pub fn first_start(&self) -> DateTime<Utc> {
+        let tz = self.tz();
+        iter::once(self.start)
+            .chain(self.overrides.iter().map(|o| o.start))
+            .map(|start| zone::resolve_local(tz, start).with_timezone(&Utc))
+            .min()
+            .unwrap()
+    }
+
+    /// 最後の発生の終了日時（終わりのない繰り返しなら None）
+    pub fn last_end(&self) -> Option<DateTime<Utc>> {
+        match &self.recurrence {
+            Some(rule) if rule.count.is_none() && rule.until.is_none() => None,
+            _ => self
+                .regular_occurrences()
+                .chain(self.overridden_occurrences())
+                .map(|occurrence| occurrence.end.with_timezone(&Utc))
+                .max(),
+        }
+    }
+
+    /// `at` がこの繰り返し予定の（除外されていない）発生の開始日時かどうか
+    pub fn has_occurrence_at(&self, at: NaiveDateTime) -> bool {
+        !self.exdates.contains(&at) && self.generates(at)
+    }
+
+    /// 繰り返しルールが `at` に始まる回を生むかどうか（取り消した回も含む）
+    fn generates(&self, at: NaiveDateTime) -> bool {
+        self.recurrence.as_ref().is_some_and(|rule| {
+            rule.starts(self.start, self.tz())
+                .take_while(|start| *start <= at)
+                .any(|start| start == at)
+        })
+    }
+
+    /// 2つの予定の発生が時間的に重なるかどうか
+    ///
+    /// 時間を占めない終日の予定 ([`Availability::Free`]) はどの予定とも重ならない。
+    pub fn intersects(&self, other: &Schedule) -> bool {
+        self.overlap(other).is_some()
+    }
+
+    /// `other` の発生のうち、この予定の発生と時間的に重なる最初のもの
+    pub fn overlap<'a>(&self, other: &'a Schedule) -> Option<Occurrence<'a>> {
+        if !self.is_busy() || !other.is_busy() {
+            return None;
+        }
+
+        // タイムゾーンの違う予定同士も比較できるよう、絶対時刻で比べる
+        if self.recurrence.is_none() && other.recurrence.is_none() {
+            let (start, end) = (
+                zone::resolve_local(self.tz(), self.start),
+                zone::resolve_local(self.tz(), self.end),
+            );
+            let occurrence = other.regular_occurrences().next()?;
+            return (start < occurrence.end && occurrence.start < end).then_some(occurrence);
+        }
+
+        // 重なりうるのは両方の予定が始まってから、どちらかが終わるまでの間
+        let from = self.first_start().max(other.first_start());
+        let to = match (self.last_end(), other.last_end()) {
+            (Some(a), Some(b)) => a.min(b),
+            (Some(end), None) | (None, Some(end)) => end,
+            (None, None) => from + Duration::days(CONFLICT_HORIZON_DAYS),
+        };
+        if from >= to {
+            return None;
+        }
+
+        // 開始順に並べ、相手側でそれまでに始まった発生の最も遅い終了と比較する
+        let others = other.occurrences_between(from, to);
+        let mut spans: Vec<(DateTime<Tz>, DateTime<Tz>, usize, usize)> = self
+            .occurrences_between(from, to)
+            .iter()
+            .enumerate()
+            .map(|(index, occurrence)| (occurrence.start, occurrence.end, 0, index))
+            .chain(
+                others
+                    .iter()
+                    .enumerate()
+                    .map(|(index, occurrence)| (occurrence.start, occurrence.end, 1, index)),
+            )
+            .collect();
+        spans.sort_by_key(|&(start, end, _, _)| (start, end));
+        // 両側それぞれの、それまでに始まった発生のうち最も遅く終わるもの
+        let mut latest: [Option<(DateTime<Tz>, usize)>; 2] = [None, None];
+        for (start, end, side, index) in spans {
+            if let Some((_, latest_index)) = latest[1 - side].filter(|(end, _)| *end > start) {
+                return Some(others[if side == 1 { index } else { latest_index }]);
+            }
+            if latest[side].is_none_or(|(latest_end, _)| end > latest_end) {
+                latest[side] = Some((end, index));
+            }
+        }
+        None
+    }
+}
+
+/// `tz` での `start` から `end` までを1回分の予定の期間として見たときの問題点
+fn span_problem(tz: Tz, start: NaiveDateTime, end: NaiveDateTime) -> Option<String> {
+    let length = zone::resolve_local(tz, end) - zone::resolve_local(tz, start);
+    if length <= Duration::zero() {
+        Some("終了日時が開始日時より後ではありません".to_string())
+    } else if length > Duration::days(MAX_DURATION_DAYS) {
+        Some(format!("長さが {} 日を超えています", MAX_DURATION_DAYS))
+    } else {
+        None
+    }
+}
+
+/// 終日の予定の期間として、開始・終了がどちらも 0 時かどうか
+fn is_midnight(start: NaiveDateTime, end: NaiveDateTime) -> bool {
+    start.time() == NaiveTime::MIN && end.time() == NaiveTime::MIN
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::native_date_time;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(18, 15, 18, 45, false)]
+    #[case(18, 15, 19, 45, true)]
+    #[case(18, 15, 20, 45, true)]
+    #[case(19, 15, 19, 45, true)]
+    #[case(19, 15, 20, 45, true)]
+    #[case(20, 15, 20, 45, false)]
+    fn test_schedule_intersects(
+        #[case] h0: u32,
+        #[case] m0: u32,
+        #[case] h1: u32,
+        #[case] m1: u32,
+        #[case] should_intersect: bool,
+    ) {
+        let schedule = Schedule::new(
+            1,
+            "既存予定".to_string(),
+            native_date_time(2024, 1, 1, h0, m0, 0),
+            native_date_time(2024, 1, 1, h1, m1, 0),
+        );
+        let new_schedule = Schedule::new(
+            999,
+            "新規予定".to_string(),
+            native_date_time(2024, 1, 1, 19, 0, 0),
+            native_date_time(2024, 1, 1, 20, 0, 0),
+        );
+        assert_eq!(schedule.intersects(&new_schedule), should_intersect);
+    }
+
+    #[rstest]
+    #[case(native_date_time(2024, 1, 15, 9, 30, 0), None, true)]
+    #[case(native_date_time(2024, 1, 16, 9, 30, 0), None, false)]
+    #[case(native_date_time(2023, 12, 25, 9, 30, 0), None, false)]
+    #[case(native_date_time(2024, 2, 5, 9, 30, 0), None, false)]
+    #[case(
+        native_date_time(2023, 12, 1, 9, 30, 0),
+        Some("FREQ=MONTHLY;BYMONTHDAY=15"),
+        true
+    )]
+    #[case(
+        native_date_time(2023, 12, 1, 9, 30, 0),
+        Some("FREQ=MONTHLY;BYMONTHDAY=16"),
+        false
+    )]
+    fn test_schedule_intersects_recurring(
+        #[case] start: NaiveDateTime,
+        #[case] rrule: Option<&str>,
+        #[case] should_intersect: bool,
+    ) {
+        // 2024/1/1 から毎週月曜 9:00-10:00 の4回
+        let standup = Schedule {
+            recurrence: Some("FREQ=WEEKLY;BYDAY=MO;COUNT=4".parse().unwrap()),
+            ..Schedule::new(
+                1,
+                "定例".to_string(),
+                native_date_time(2024, 1, 1, 9, 0, 0),
+                native_date_time(2024, 1, 1, 10, 0, 0),
+            )
+        };
+        let new_schedule = Schedule {
+            recurrence: rrule.map(|rrule| rrule.parse().unwrap()),
+            ..Schedule::new(
+                999,
+                "新規予定".to_string(),
+                start,
+                start + Duration::minutes(60),
+            )
+        };
+        assert_eq!(standup.intersects(&new_schedule), should_intersect);
+        assert_eq!(new_schedule.intersects(&standup), should_intersect);
+    }
+
+    #[rstest]
+    #[case(chrono_tz::Asia::Tokyo, 18, true)]
+    #[case(chrono_tz::Asia::Tokyo, 9, false)]
+    #[case(chrono_tz::Europe::Berlin, 10, true)]
+    #[case(chrono_tz::Europe::Berlin, 8, false)]
+    fn test_schedule_intersects_across_time_zones(
+        #[case] tz: Tz,
+        #[case] hour: u32,
+        #[case] should_intersect: bool,
+    ) {
+        // UTC 9:00-10:00 の予定と、各地の時刻で1時間の予定を比べる
+        let schedule = Schedule {
+            tz: Some(Tz::UTC),
+            ..Schedule::new(
+                1,
+                "既存予定".to_string(),
+                native_date_time(2024, 1, 1, 9, 0, 0),
+                native_date_time(2024, 1, 1, 10, 0, 0),
+            )
+        };
+        let new_schedule = Schedule {
+            tz: Some(tz),
+            ..Schedule::new(
+                999,
+                "新規予定".to_string(),
+                native_date_time(2024, 1, 1, hour, 30, 0),
+                native_date_time(2024, 1, 1, hour + 1, 30, 0),
+            )
+        };
+        assert_eq!(schedule.intersects(&new_schedule), should_intersect);
+    }
+
+    #[rstest]
+    // 時間を占める終日の予定は、その日の時刻のある予定と重なる
+    #[case(Availability::Busy, native_date_time(2024, 1, 2, 9, 0, 0), true)]
+    #[case(Availability::Free, native_date_time(2024, 1, 2, 9, 0, 0), false)]
+    // 最終日の翌日とは重ならない
+    #[case(Availability::Busy, native_date_time(2024, 1, 4, 0, 0, 0), false)]
+    #[case(Availability::Busy, native_date_time(2023, 12, 31, 23, 0, 0), true)]
+    fn test_schedule_intersects_all_day(
+        #[case] availability: Availability,
+        #[case] start: NaiveDateTime,
+        #[case] should_intersect: bool,
+    ) {
+        // 1/1 から 1/3 までの出張
+        let trip = Schedule::new_all_day(
+            1,
+            "出張".to_string(),
+            NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(),
+            availability,
+        );
+        let new_schedule = Schedule::new(
+            999,
+            "新規予定".to_string(),
+            start,
+            start + Duration::hours(2),
+        );
+        assert_eq!(trip.intersects(&new_schedule), should_intersect);
+        assert_eq!(new_schedule.intersects(&trip), should_intersect);
+    }
+
+    #[test]
+    fn test_recurring_schedule_keeps_local_time_across_dst() {
+        // ベルリンで毎週月曜 9:00 の予定は、夏時間の前後で UTC の時刻が変わる
+        let schedule = Schedule {
+            tz: Some(chrono_tz::Europe::Berlin),
+            recurrence: Some("FREQ=WEEKLY;COUNT=2".parse().unwrap()),
+            ..Schedule::new(
+                0,
+                "定例".to_string(),
+                native_date_time(2024, 3, 25, 9, 0, 0),
+                native_date_time(2024, 3, 25, 10, 0, 0),
+            )
+        };
+        let starts: Vec<NaiveDateTime> = schedule
+            .regular_occurrences()
+            .map(|occurrence| occurrence.start.naive_utc())
+            .collect();
+        assert_eq!(
+            starts,
+            vec![
+                native_date_time(2024, 3, 25, 8, 0, 0),
+                native_date_time(2024, 4, 1, 7, 0, 0),
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case("定例", (9, 0), (10, 0), vec![])]
+    #[case(" ", (9, 0), (10, 0), vec!["タイトルが空です"])]
+    #[case("定例", (9, 0), (9, 0), vec!["終了日時が開始日時より後ではありません"])]
+    #[case("定例", (10, 0), (9, 0), vec!["終了日時が開始日時より後ではありません"])]
+    #[case("", (10, 0), (9, 0), vec!["タイトルが空です", "終了日時が開始日時より後ではありません"])]
+    fn test_schedule_problems(
+        #[case] subject: &str,
+        #[case] start: (u32, u32),
+        #[case] end: (u32, u32),
+        #[case] expected: Vec<&str>,
+    ) {
+        let schedule = Schedule::new(
+            0,
+            subject.to_string(),
+            native_date_time(2024, 1, 1, start.0, start.1, 0),
+            native_date_time(2024, 1, 1, end.0, end.1, 0),
+        );
+        assert_eq!(schedule.problems(), expected);
+    }
+
+    #[test]
+    fn test_all_day_schedule_problems() {
+        let schedule = Schedule {
+            all_day: Some(Availability::Busy),
+            ..Schedule::new(
+                0,
+                "休暇".to_string(),
+                native_date_time(2024, 1, 1, 0, 0, 0),
+                native_date_time(2024, 1, 2, 9, 0, 0),
+            )
+        };
+        assert_eq!(
+            schedule.problems(),
+            vec!["終日の予定の開始・終了日時が 0 時ではありません"]
+        );
+    }
+
+    #[test]
+    fn test_orphaned_occurrence_problems() {
+        // 繰り返しが生まない回を取り消したり変更したりしていれば問題とする
+        let schedule = Schedule {
+            recurrence: Some("FREQ=WEEKLY;COUNT=4".parse().unwrap()),
+            exdates: vec![
+                native_date_time(2024, 1, 8, 9, 0, 0),
+                native_date_time(2024, 1, 9, 9, 0, 0),
+            ],
+            overrides: vec![Override {
+                recurrence_id: native_date_time(2024, 1, 29, 9, 0, 0),
+                subject: None,
+                start: native_date_time(2024, 1, 29, 13, 0, 0),
+                end: native_date_time(2024, 1, 29, 14, 0, 0),
+            }],
+            ..Schedule::new(
+                0,
+                "定例".to_string(),
+                native_date_time(2024, 1, 1, 9, 0, 0),
+                native_date_time(2024, 1, 1, 10, 0, 0),
+            )
+        };
+        assert_eq!(
+            schedule.problems(),
+            vec![
+                "取り消した 2024-01-09 09:00:00 開始の回は繰り返しにありません",
+                "変更した 2024-01-29 09:00:00 開始の回は繰り返しにありません",
+            ]
+        );
+    }
+}
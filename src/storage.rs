@@ -0,0 +1,606 @@
+use crate::{
+    error::{CalendarError, Error},
+    zone, Calendar, Schedule, ScheduleId,
+};
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
+
+/// 予定の保存先
+///
+/// 保存するだけで、重複や予定の正しさは確かめない（[`Calendar`] の操作で確かめてから保存する）。
+///
+/// 予定を変更するときは [`Store::load_for_change`] で読み込んだカレンダーを変更し、変更後の
+/// カレンダーを [`Store::insert`]・[`Store::update`]・[`Store::delete`] に渡す。
+pub trait Store {
+    /// 保存先のファイルのパス
+    fn path(&self) -> &Path;
+
+    /// 保存されているカレンダーを読み込む
+    fn load(&self) -> Result<Calendar, Error>;
+
+    /// カレンダー全体を保存する（保存先がなければ作成する）
+    fn save(&self, calendar: &Calendar) -> Result<(), Error>;
+
+    /// 予定を変更するために、`ids` の予定と `range` と重なる発生がありうる予定を読み込む
+    ///
+    /// 重複判定には変更後の予定と重なりうる予定があれば足りる。既定では全体を読み込む。
+    fn load_for_change(
+        &self,
+        ids: &[ScheduleId],
+        range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    ) -> Result<Calendar, Error> {
+        let _ = (ids, range);
+        self.load()
+    }
+
+    /// `calendar` に追加した `ids` の予定を保存する
+    fn insert(&self, calendar: &Calendar, ids: &[ScheduleId]) -> Result<(), Error>;
+
+    /// `calendar` で変更した `id` の予定を保存する
+    fn update(&self, calendar: &Calendar, id: ScheduleId) -> Result<(), Error>;
+
+    /// `calendar` から削除した `ids` の予定を保存先からも削除する
+    fn delete(&self, calendar: &Calendar, ids: &[ScheduleId]) -> Result<(), Error>;
+
+    /// `[from, to)` と重なる発生がありうる予定を読み込む
+    fn query(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Calendar, Error>;
+
+    /// 書き込むために保存先の排他ロックを取る（他のプロセスが持っていれば解放されるまで待つ）
+    ///
+    /// 読むだけなら要らない。予定ファイルは名前の変更で丸ごと置き換え、データベースは
+    /// SQLite がトランザクションで守るので、書き込みの途中の内容を読むことはない。
+    fn lock(&self) -> Result<FileLock, Error> {
+        let path = self.path();
+        let dir = parent_dir(path);
+        fs::create_dir_all(dir).map_err(Error::io(dir))?;
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".lock");
+        let lock_path = dir.join(name);
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(Error::io(&lock_path))?;
+        file.lock().map_err(Error::io(&lock_path))?;
+        Ok(FileLock(file))
+    }
+}
+
+/// JSON の予定ファイル
+#[derive(Debug, Clone)]
+pub struct JsonStore {
+    path: PathBuf,
+}
+
+impl JsonStore {
+    /// `path` の予定ファイルを読み書きする
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        JsonStore { path: path.into() }
+    }
+}
+
+impl Store for JsonStore {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load(&self) -> Result<Calendar, Error> {
+        let path = &self.path;
+        let content = fs::read_to_string(path).map_err(|source| match source.kind() {
+            io::ErrorKind::NotFound => Error::FileNotFound(path.to_path_buf()),
+            _ => Error::io(path)(source),
+        })?;
+        // 空のファイルは予定のないカレンダーとして扱う
+        let calendar: Calendar = if content.trim().is_empty() {
+            Calendar::default()
+        } else {
+            serde_json::from_str(&content).map_err(|source| Error::Parse {
+                path: path.to_path_buf(),
+                source,
+            })?
+        };
+        Ok(checked(calendar))
+    }
+
+    /// 予定ファイルを置き換える
+    ///
+    /// 同じディレクトリの一時ファイルに書き込んで fsync してから名前を変えるので、
+    /// 途中で止まっても元のファイルか新しいファイルのどちらかが残る。
+    fn save(&self, calendar: &Calendar) -> Result<(), Error> {
+        let path = &self.path;
+        // 既定の保存先のディレクトリはまだないことがある
+        let dir = parent_dir(path);
+        fs::create_dir_all(dir).map_err(Error::io(dir))?;
+        let mut name = OsString::from(".");
+        name.push(path.file_name().unwrap_or_default());
+        name.push(format!(".{}.tmp", process::id()));
+        let temp = dir.join(name);
+
+        let written = write_calender(calendar, &temp).and_then(|()| {
+            fs::rename(&temp, path).map_err(Error::io(path))?;
+            // 名前の変更もディスクに書き出す（ディレクトリを開けない環境では諦める）
+            if let Ok(dir) = File::open(dir) {
+                let _ = dir.sync_all();
+            }
+            Ok(())
+        });
+        if written.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        written
+    }
+
+    /// 全体を読み込んで変更したカレンダーなので、そのまま保存する
+    fn insert(&self, calendar: &Calendar, _ids: &[ScheduleId]) -> Result<(), Error> {
+        self.save(calendar)
+    }
+
+    fn update(&self, calendar: &Calendar, _id: ScheduleId) -> Result<(), Error> {
+        self.save(calendar)
+    }
+
+    fn delete(&self, calendar: &Calendar, _ids: &[ScheduleId]) -> Result<(), Error> {
+        self.save(calendar)
+    }
+
+    /// ファイル全体を読んでから絞り込む
+    fn query(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Calendar, Error> {
+        let calendar = self.load()?;
+        let schedules = calendar
+            .schedules_between(from, to)
+            .into_iter()
+            .cloned()
+            .collect();
+        Ok(Calendar::new(schedules, calendar.next_id()))
+    }
+}
+
+/// SQLite の予定データベース
+///
+/// 予定ごとに1行で保存し、最初の発生の開始と最後の発生の終了に索引を張るので、
+/// 期間での検索や1件ごとの書き込みでデータベース全体を読み書きしない。
+#[derive(Debug, Clone)]
+pub struct SqliteStore {
+    path: PathBuf,
+}
+
+/// 予定データベースの表（予定そのものは `data` に JSON で保存する）
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS schedules (
+        id INTEGER PRIMARY KEY,
+        uid TEXT NOT NULL,
+        first_start INTEGER NOT NULL,
+        last_end INTEGER,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS schedules_range ON schedules (first_start, last_end);
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value INTEGER NOT NULL
+    );
+";
+
+/// 他のプロセスが書き込み中のとき待つ時間の上限
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl SqliteStore {
+    /// `path` の予定データベースを読み書きする
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        SqliteStore { path: path.into() }
+    }
+
+    /// データベースを開く（`create` でなければ、ないときはエラーにする）
+    fn connect(&self, create: bool) -> Result<Connection, Error> {
+        let path = &self.path;
+        let mut flags = OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+        if create {
+            let dir = parent_dir(path);
+            fs::create_dir_all(dir).map_err(Error::io(dir))?;
+            flags |= OpenFlags::SQLITE_OPEN_CREATE;
+        } else if !path.exists() {
+            return Err(Error::FileNotFound(path.to_path_buf()));
+        }
+        let connection = Connection::open_with_flags(path, flags).map_err(Error::database(path))?;
+        connection
+            .busy_timeout(BUSY_TIMEOUT)
+            .and_then(|()| connection.execute_batch(SCHEMA))
+            .map_err(Error::database(path))?;
+        Ok(connection)
+    }
+
+    /// `schedules` の行を読んでカレンダーにする
+    fn read(
+        &self,
+        connection: &Connection,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Calendar, Error> {
+        let path = &self.path;
+        let mut statement = connection.prepare(sql).map_err(Error::database(path))?;
+        let rows = statement
+            .query_map(params, |row| row.get::<_, String>(0))
+            .map_err(Error::database(path))?;
+        let mut schedules = Vec::new();
+        for data in rows {
+            let data = data.map_err(Error::database(path))?;
+            let schedule = serde_json::from_str(&data).map_err(|source| Error::Parse {
+                path: path.to_path_buf(),
+                source,
+            })?;
+            schedules.push(schedule);
+        }
+        let next_id = connection
+            .query_row("SELECT value FROM meta WHERE key = 'next_id'", [], |row| {
+                row.get(0)
+            })
+            .optional()
+            .map_err(Error::database(path))?
+            .unwrap_or(0);
+        Ok(checked(Calendar::new(schedules, next_id)))
+    }
+}
+
+/// 予定を `schedules` の1行に書き込む（同じIDの行があれば置き換える）
+fn write_row(connection: &Connection, schedule: &Schedule) -> rusqlite::Result<usize> {
+    let data = serde_json::to_string(schedule)
+        .map_err(|error| rusqlite::Error::ToSqlConversionFailure(Box::new(error)))?;
+    connection.execute(
+        "INSERT OR REPLACE INTO schedules (id, uid, first_start, last_end, data)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        (
+            schedule.id as i64,
+            &schedule.uid,
+            schedule.first_start().timestamp(),
+            schedule.last_end().map(|end| end.timestamp()),
+            data,
+        ),
+    )
+}
+
+/// 払い出し済みのIDを `id` まで進める
+fn advance_next_id(connection: &Connection, id: ScheduleId) -> rusqlite::Result<usize> {
+    connection.execute(
+        "INSERT INTO meta (key, value) VALUES ('next_id', ?1)
+         ON CONFLICT (key) DO UPDATE SET value = max(value, excluded.value)",
+        [id as i64 + 1],
+    )
+}
+
+impl Store for SqliteStore {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load(&self) -> Result<Calendar, Error> {
+        let connection = self.connect(false)?;
+        self.read(&connection, "SELECT data FROM schedules ORDER BY id", [])
+    }
+
+    fn save(&self, calendar: &Calendar) -> Result<(), Error> {
+        let path = &self.path;
+        let mut connection = self.connect(true)?;
+        let transaction = connection.transaction().map_err(Error::database(path))?;
+        transaction
+            .execute_batch("DELETE FROM schedules; DELETE FROM meta;")
+            .map_err(Error::database(path))?;
+        for schedule in calendar.schedules() {
+            write_row(&transaction, schedule).map_err(Error::database(path))?;
+        }
+        if calendar.next_id() > 0 {
+            advance_next_id(&transaction, calendar.next_id() - 1).map_err(Error::database(path))?;
+        }
+        transaction.commit().map_err(Error::database(path))
+    }
+
+    fn load_for_change(
+        &self,
+        ids: &[ScheduleId],
+        range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    ) -> Result<Calendar, Error> {
+        let connection = self.connect(false)?;
+        let ids = serde_json::to_string(ids).unwrap();
+        // 期間がなければ比較が NULL になり、IDだけで選ぶ
+        self.read(
+            &connection,
+            "SELECT data FROM schedules
+             WHERE id IN (SELECT value FROM json_each(?1))
+                OR (first_start < ?2 AND (last_end IS NULL OR last_end > ?3))
+             ORDER BY id",
+            (
+                ids,
+                range.map(|(_, to)| to.timestamp()),
+                range.map(|(from, _)| from.timestamp()),
+            ),
+        )
+    }
+
+    /// 1つのトランザクションで追加する（他の予定の行は書き直さない）
+    fn insert(&self, calendar: &Calendar, ids: &[ScheduleId]) -> Result<(), Error> {
+        let path = &self.path;
+        let mut connection = self.connect(false)?;
+        let transaction = connection.transaction().map_err(Error::database(path))?;
+        for &id in ids {
+            let schedule = calendar.find(id).ok_or(CalendarError::NotFound(id))?;
+            write_row(&transaction, schedule)
+                .and_then(|_| advance_next_id(&transaction, schedule.id))
+                .map_err(Error::database(path))?;
+        }
+        transaction.commit().map_err(Error::database(path))
+    }
+
+    /// 行があることを確かめてから置き換えるまでを1つのトランザクションで行う
+    fn update(&self, calendar: &Calendar, id: ScheduleId) -> Result<(), Error> {
+        let path = &self.path;
+        let schedule = calendar.find(id).ok_or(CalendarError::NotFound(id))?;
+        let mut connection = self.connect(false)?;
+        let transaction = connection.transaction().map_err(Error::database(path))?;
+        let exists = transaction
+            .query_row(
+                "SELECT 1 FROM schedules WHERE id = ?1",
+                [schedule.id as i64],
+                |_| Ok(()),
+            )
+            .optional()
+            .map_err(Error::database(path))?;
+        if exists.is_none() {
+            return Err(CalendarError::NotFound(schedule.id).into());
+        }
+        write_row(&transaction, schedule)
+            .and_then(|_| transaction.commit())
+            .map_err(Error::database(path))
+    }
+
+    /// 1つのトランザクションで削除する（見つからないIDがあれば何も削除しない）
+    fn delete(&self, _calendar: &Calendar, ids: &[ScheduleId]) -> Result<(), Error> {
+        let path = &self.path;
+        let mut connection = self.connect(false)?;
+        let transaction = connection.transaction().map_err(Error::database(path))?;
+        for &id in ids {
+            let deleted = transaction
+                .execute("DELETE FROM schedules WHERE id = ?1", [id as i64])
+                .map_err(Error::database(path))?;
+            if deleted == 0 {
+                return Err(CalendarError::NotFound(id).into());
+            }
+        }
+        transaction.commit().map_err(Error::database(path))
+    }
+
+    fn query(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Calendar, Error> {
+        let connection = self.connect(false)?;
+        self.read(
+            &connection,
+            "SELECT data FROM schedules
+             WHERE first_start < ?1 AND (last_end IS NULL OR last_end > ?2)
+             ORDER BY id",
+            (to.timestamp(), from.timestamp()),
+        )
+    }
+}
+
+/// 読み込んだカレンダーを使える状態にする
+///
+/// タイムゾーンのない古い予定は既定のタイムゾーンの時刻とみなす。正しくない予定があっても、
+/// 直したり削除したりできるようにそのまま読み込む（問題は [`Calendar::validate`] で確かめる）。
+fn checked(mut calendar: Calendar) -> Calendar {
+    calendar.fill_default_tz(zone::default_tz());
+    calendar
+}
+
+/// 予定ファイルのあるディレクトリ
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// 予定の書き込みを他のプロセスと排他する勧告ロック（drop で解放する）
+///
+/// 予定ファイルは保存のたびに置き換わるので、隣に置いた `<ファイル名>.lock` をロックする。
+/// ロックを待っている他のプロセスと別のファイルをロックしてしまわないよう、`.lock` は
+/// 解放しても消さずに残す。
+pub struct FileLock(File);
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.0.unlock();
+    }
+}
+
+/// `path` に新しく書き込み、ディスクに書き出されるまで待つ
+fn write_calender(calendar: &Calendar, path: &Path) -> Result<(), Error> {
+    let file = File::create(path).map_err(Error::io(path))?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer(&mut writer, calendar).map_err(|source| Error::Io {
+        path: path.to_path_buf(),
+        source: source.into(),
+    })?;
+    let file = writer
+        .into_inner()
+        .map_err(|error| Error::io(path)(error.into_error()))?;
+    file.sync_all().map_err(Error::io(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::native_date_time;
+    use chrono::NaiveDateTime;
+    use chrono_tz::Tz;
+    use rstest::rstest;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_save_calender() {
+        let dir = std::env::temp_dir().join(format!("calendar-test-{}", Uuid::new_v4()));
+        let path = dir.join("schedules.json");
+        let store = JsonStore::new(&path);
+        let mut calendar = Calendar::default();
+        let _lock = store.lock().unwrap();
+        store.save(&calendar).unwrap();
+
+        // 置き換えても一時ファイルは残らない
+        calendar
+            .add(Schedule {
+                tz: Some(Tz::UTC),
+                ..Schedule::new(
+                    0,
+                    "テスト予定".to_string(),
+                    native_date_time(2024, 1, 1, 9, 0, 0),
+                    native_date_time(2024, 1, 1, 10, 0, 0),
+                )
+            })
+            .unwrap();
+        store.save(&calendar).unwrap();
+        assert_eq!(store.load().unwrap(), calendar);
+        let mut names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["schedules.json", "schedules.json.lock"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_legacy_file_keeps_uid() {
+        // UID のない古いファイルは、何度読み込んでも同じ UID になる
+        let dir = std::env::temp_dir().join(format!("calendar-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("schedules.json");
+        fs::write(
+            &path,
+            r#"{"schedules":[{"id":3,"subject":"定例","start":"2024-01-01T09:00:00","end":"2024-01-01T10:00:00"}]}"#,
+        )
+        .unwrap();
+        let store = JsonStore::new(&path);
+        let first = store.load().unwrap();
+        assert_eq!(first.schedules()[0].uid, "3-20240101T090000@calendar");
+        assert_eq!(store.load().unwrap(), first);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[rstest]
+    #[case("schedules.json")]
+    #[case("schedules.db")]
+    fn test_store(#[case] name: &str) {
+        let dir = std::env::temp_dir().join(format!("calendar-test-{}", Uuid::new_v4()));
+        let path = dir.join(name);
+        let store: Box<dyn Store> = if name.ends_with(".db") {
+            Box::new(SqliteStore::new(&path))
+        } else {
+            Box::new(JsonStore::new(&path))
+        };
+        assert!(matches!(store.load(), Err(Error::FileNotFound(_))));
+        store.save(&Calendar::default()).unwrap();
+
+        // 1/1 から毎日 9:00-10:00 を3回と、1/10 の単発の予定をまとめて追加する
+        let mut calendar = Calendar::default();
+        let ids: Vec<ScheduleId> = [
+            Schedule {
+                recurrence: Some("FREQ=DAILY;COUNT=3".parse().unwrap()),
+                ..Schedule::new(
+                    0,
+                    "朝会".to_string(),
+                    native_date_time(2024, 1, 1, 9, 0, 0),
+                    native_date_time(2024, 1, 1, 10, 0, 0),
+                )
+            },
+            Schedule::new(
+                0,
+                "レビュー".to_string(),
+                native_date_time(2024, 1, 10, 9, 0, 0),
+                native_date_time(2024, 1, 10, 10, 0, 0),
+            ),
+        ]
+        .into_iter()
+        .map(|schedule| {
+            calendar
+                .add(Schedule {
+                    tz: Some(Tz::UTC),
+                    ..schedule
+                })
+                .unwrap()
+        })
+        .collect();
+        store.insert(&calendar, &ids).unwrap();
+        assert_eq!(store.load().unwrap(), calendar);
+
+        let ids = |from: NaiveDateTime, to: NaiveDateTime| -> Vec<ScheduleId> {
+            store
+                .query(from.and_utc(), to.and_utc())
+                .unwrap()
+                .schedules()
+                .iter()
+                .map(|schedule| schedule.id)
+                .collect()
+        };
+        assert_eq!(
+            ids(
+                native_date_time(2024, 1, 3, 9, 30, 0),
+                native_date_time(2024, 1, 5, 0, 0, 0)
+            ),
+            vec![0]
+        );
+        assert_eq!(
+            ids(
+                native_date_time(2024, 1, 3, 10, 0, 0),
+                native_date_time(2024, 1, 10, 9, 0, 0)
+            ),
+            Vec::<ScheduleId>::new()
+        );
+
+        // SQLite は指定したIDの予定と期間と重なりうる予定だけを読み込む（JSON は全体）
+        let sqlite = name.ends_with(".db");
+        let ids_for_change = |ids: &[ScheduleId], range: Option<(NaiveDateTime, NaiveDateTime)>| {
+            store
+                .load_for_change(ids, range.map(|(from, to)| (from.and_utc(), to.and_utc())))
+                .unwrap()
+                .schedules()
+                .iter()
+                .map(|schedule| schedule.id)
+                .collect::<Vec<ScheduleId>>()
+        };
+        assert_eq!(
+            ids_for_change(&[1], None),
+            if sqlite { vec![1] } else { vec![0, 1] }
+        );
+        assert_eq!(
+            ids_for_change(
+                &[],
+                Some((
+                    native_date_time(2024, 1, 3, 9, 30, 0),
+                    native_date_time(2024, 1, 5, 0, 0, 0)
+                ))
+            ),
+            if sqlite { vec![0] } else { vec![0, 1] }
+        );
+
+        calendar
+            .edit(1, Some("設計レビュー".to_string()), None, None, None)
+            .unwrap();
+        store.update(&calendar, 1).unwrap();
+        calendar.remove(0).unwrap();
+        store.delete(&calendar, &[0]).unwrap();
+        assert_eq!(store.load().unwrap(), calendar);
+        if sqlite {
+            assert!(matches!(
+                store.delete(&calendar, &[1, 0]),
+                Err(Error::Calendar(CalendarError::NotFound(0)))
+            ));
+            // 途中で見つからなければ何も削除しない
+            assert_eq!(store.load().unwrap(), calendar);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,177 @@
+use std::str::FromStr;
+
+/// タグの指定を正規化する（小文字にそろえる）
+///
+/// 絞り込みの条件と区別できなくなるので、空白・括弧・カンマを含むものと `and`・`or`・`not` は使えない。
+pub fn normalize(tag: &str) -> Result<String, String> {
+    let tag = tag.trim().to_lowercase();
+    if tag.is_empty() {
+        return Err("タグが空です".to_string());
+    }
+    if tag
+        .chars()
+        .any(|c| c.is_whitespace() || matches!(c, '(' | ')' | ','))
+    {
+        return Err(format!("タグ「{}」に空白・括弧・カンマは使えません", tag));
+    }
+    if matches!(tag.as_str(), "and" | "or" | "not") {
+        return Err(format!("「{}」はタグに使えません", tag));
+    }
+    Ok(tag)
+}
+
+/// タグによる絞り込みの条件（例: `work and not (private or 1on1)`）
+///
+/// `not`、`and`、`or` の順に強く結び付き、括弧でまとめられる。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagQuery {
+    /// このタグが付いている
+    Tag(String),
+    /// 条件に合わない
+    Not(Box<TagQuery>),
+    /// 両方の条件に合う
+    And(Box<TagQuery>, Box<TagQuery>),
+    /// どちらかの条件に合う
+    Or(Box<TagQuery>, Box<TagQuery>),
+}
+
+impl TagQuery {
+    /// `tags` が付いた予定が条件に合うかどうか
+    pub fn matches(&self, tags: &[String]) -> bool {
+        match self {
+            TagQuery::Tag(tag) => tags.iter().any(|t| t.to_lowercase() == *tag),
+            TagQuery::Not(query) => !query.matches(tags),
+            TagQuery::And(a, b) => a.matches(tags) && b.matches(tags),
+            TagQuery::Or(a, b) => a.matches(tags) || b.matches(tags),
+        }
+    }
+}
+
+impl FromStr for TagQuery {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut tokens = Vec::new();
+        for word in s.split_whitespace() {
+            let mut rest = word;
+            while !rest.is_empty() {
+                let end = match rest.find(['(', ')']) {
+                    Some(0) => 1,
+                    Some(end) => end,
+                    None => rest.len(),
+                };
+                tokens.push(&rest[..end]);
+                rest = &rest[end..];
+            }
+        }
+        let mut parser = Parser { tokens, next: 0 };
+        let query = parser.or()?;
+        match parser.tokens.get(parser.next) {
+            None => Ok(query),
+            Some(token) => Err(format!("「{}」の位置で条件を解釈できません", token)),
+        }
+    }
+}
+
+/// 再帰下降で条件を読む
+struct Parser<'a> {
+    tokens: Vec<&'a str>,
+    next: usize,
+}
+
+impl Parser<'_> {
+    /// 次の字句が `keyword` なら読み進める
+    fn eat(&mut self, keyword: &str) -> bool {
+        let matched = self
+            .tokens
+            .get(self.next)
+            .is_some_and(|token| token.eq_ignore_ascii_case(keyword));
+        if matched {
+            self.next += 1;
+        }
+        matched
+    }
+
+    fn or(&mut self) -> Result<TagQuery, String> {
+        let mut query = self.and()?;
+        while self.eat("or") {
+            query = TagQuery::Or(Box::new(query), Box::new(self.and()?));
+        }
+        Ok(query)
+    }
+
+    fn and(&mut self) -> Result<TagQuery, String> {
+        let mut query = self.not()?;
+        while self.eat("and") {
+            query = TagQuery::And(Box::new(query), Box::new(self.not()?));
+        }
+        Ok(query)
+    }
+
+    fn not(&mut self) -> Result<TagQuery, String> {
+        if self.eat("not") {
+            return Ok(TagQuery::Not(Box::new(self.not()?)));
+        }
+        if self.eat("(") {
+            let query = self.or()?;
+            if !self.eat(")") {
+                return Err("括弧が閉じられていません".to_string());
+            }
+            return Ok(query);
+        }
+        match self.tokens.get(self.next) {
+            None => Err("タグがありません".to_string()),
+            Some(token) => {
+                let tag = normalize(token)?;
+                self.next += 1;
+                Ok(TagQuery::Tag(tag))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("work", vec!["work"], true)]
+    #[case("WORK", vec!["work"], true)]
+    #[case("work", vec!["private"], false)]
+    #[case("work and 1on1", vec!["work"], false)]
+    #[case("work and 1on1", vec!["1on1", "work"], true)]
+    #[case("work or private", vec!["private"], true)]
+    #[case("not private", vec![], true)]
+    #[case("not private", vec!["private"], false)]
+    // and は or より強く結び付く
+    #[case("private or work and 1on1", vec!["private"], true)]
+    #[case("(private or work) and 1on1", vec!["private"], false)]
+    #[case("work and not(1on1 or review)", vec!["work", "review"], false)]
+    fn test_tag_query(#[case] query: &str, #[case] tags: Vec<&str>, #[case] expected: bool) {
+        let tags: Vec<String> = tags.into_iter().map(String::from).collect();
+        let query: TagQuery = query.parse().unwrap();
+        assert_eq!(query.matches(&tags), expected);
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("work and")]
+    #[case("(work or private")]
+    #[case("work private")]
+    #[case("work)")]
+    #[case("not")]
+    fn test_tag_query_error(#[case] query: &str) {
+        assert!(query.parse::<TagQuery>().is_err());
+    }
+
+    #[rstest]
+    #[case(" Work ", Ok("work"))]
+    #[case("", Err(()))]
+    #[case("a b", Err(()))]
+    #[case("a,b", Err(()))]
+    #[case("not", Err(()))]
+    fn test_normalize(#[case] tag: &str, #[case] expected: Result<&str, ()>) {
+        assert_eq!(normalize(tag).map_err(|_| ()), expected.map(String::from));
+    }
+}
@@ -0,0 +1,23 @@
+//! テストで使う補助関数
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+/// 年月日と時分秒からタイムゾーンのない日時を作る
+pub(crate) fn native_date_time(
+    year: i32,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(year, month, day)
+        .unwrap()
+        .and_hms_opt(hour, minute, second)
+        .unwrap()
+}
+
+/// `2024-01-01T09:00:00` 形式の文字列からタイムゾーンのない日時を作る
+pub(crate) fn native_date_time_from_str(s: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").unwrap()
+}
@@ -0,0 +1,785 @@
+use crate::{clock::Clock, zone, Availability, Calendar, Occurrence, Schedule};
+use chrono::{
+    DateTime, Datelike, Days, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, Weekday,
+};
+use chrono_tz::Tz;
+use std::{fmt::Write, iter, ops::Range};
+
+/// 月表示の1日分のマスの幅（表示上の桁数）
+const CELL_WIDTH: usize = 4;
+
+/// 週表示の1日分の列の幅（表示上の桁数）
+const COLUMN_WIDTH: usize = 12;
+
+/// 曜日の表示名
+pub fn weekday_name(weekday: Weekday) -> &'static str {
+    ["月", "火", "水", "木", "金", "土", "日"][weekday.num_days_from_monday() as usize]
+}
+
+/// 端末での表示幅（ASCII と罫線ブロック以外は全角として2桁に数える）
+fn display_width(s: &str) -> usize {
+    s.chars()
+        .map(|c| {
+            if c.is_ascii() || ('\u{2580}'..='\u{259f}').contains(&c) {
+                1
+            } else {
+                2
+            }
+        })
+        .sum()
+}
+
+/// 表示幅が `width` になるよう切り詰め、空白で埋める
+fn fit_width(s: &str, width: usize) -> String {
+    let mut result = String::new();
+    let mut used = 0;
+    for c in s.chars() {
+        let w = display_width(c.encode_utf8(&mut [0; 4]));
+        if used + w > width {
+            break;
+        }
+        result.push(c);
+        used += w;
+    }
+    result.push_str(&" ".repeat(width - used));
+    result
+}
+
+/// 発生の開始・終了を `tz` での日時に変換する
+pub fn local_span(occurrence: &Occurrence, tz: Tz) -> (NaiveDateTime, NaiveDateTime) {
+    (
+        occurrence.start.with_timezone(&tz).naive_local(),
+        occurrence.end.with_timezone(&tz).naive_local(),
+    )
+}
+
+/// 終日の予定の発生の最初と最後の日付（どのタイムゾーンで表示しても同じ日付にする）
+pub fn all_day_dates(occurrence: &Occurrence) -> (NaiveDate, NaiveDate) {
+    let first = occurrence.start.date_naive();
+    let last = occurrence.end.date_naive().pred_opt().unwrap_or(first);
+    (first, last.max(first))
+}
+
+/// 発生がかかっている `tz` での日付の範囲（終了時刻ちょうどの日は含めない）
+fn covered_dates(schedule: &Schedule, occurrence: &Occurrence, tz: Tz) -> (NaiveDate, NaiveDate) {
+    if schedule.is_all_day() {
+        return all_day_dates(occurrence);
+    }
+    let (start, end) = local_span(occurrence, tz);
+    let last = if end > start {
+        (end - chrono::Duration::nanoseconds(1)).date()
+    } else {
+        start.date()
+    };
+    (start.date(), last)
+}
+
+/// `M/D(曜) HH:MM-HH:MM` 形式の期間（日をまたぐ場合は終了側にも日付を付ける）
+pub fn format_span(start: NaiveDateTime, end: NaiveDateTime) -> String {
+    let date = format!(
+        "{}/{}({})",
+        start.month(),
+        start.day(),
+        weekday_name(start.weekday())
+    );
+    if start.date() == end.date() {
+        format!("{} {}-{}", date, start.format("%H:%M"), end.format("%H:%M"))
+    } else {
+        format!(
+            "{} {}-{}/{} {}",
+            date,
+            start.format("%H:%M"),
+            end.month(),
+            end.day(),
+            end.format("%H:%M")
+        )
+    }
+}
+
+/// `M/D(曜)` 形式の日付（`first` と `last` が違えば `M/D(曜)-M/D(曜)`）
+pub fn format_dates(first: NaiveDate, last: NaiveDate) -> String {
+    let format = |date: NaiveDate| {
+        format!(
+            "{}/{}({})",
+            date.month(),
+            date.day(),
+            weekday_name(date.weekday())
+        )
+    };
+    if first == last {
+        format(first)
+    } else {
+        format!("{}-{}", format(first), format(last))
+    }
+}
+
+/// 予定（繰り返しなら最初の回）の期間を `tz` での日時で表す（終日の予定は日付だけ）
+pub fn format_schedule(schedule: &Schedule, tz: Tz) -> String {
+    if schedule.is_all_day() {
+        let last = (schedule.end.date() - Days::new(1)).max(schedule.start.date());
+        format!("{} 終日", format_dates(schedule.start.date(), last))
+    } else {
+        format_span(
+            zone::convert(schedule.start, schedule.tz(), tz),
+            zone::convert(schedule.end, schedule.tz(), tz),
+        )
+    }
+}
+
+/// 発生の期間を `tz` での日時で表す（終日の予定は日付だけ）
+pub fn format_occurrence(schedule: &Schedule, occurrence: &Occurrence, tz: Tz) -> String {
+    if schedule.is_all_day() {
+        let (first, last) = all_day_dates(occurrence);
+        format!("{} 終日", format_dates(first, last))
+    } else {
+        let (start, end) = local_span(occurrence, tz);
+        format_span(start, end)
+    }
+}
+
+/// `month` を含む月のカレンダーと、その月の予定の一覧を描画する
+///
+/// 予定のある日には `*` を付け、`highlight_today` なら今日の日付を反転表示する。
+/// 一覧では終日の予定を先に並べる。
+pub fn render_month(
+    calendar: &Calendar,
+    month: NaiveDate,
+    today: NaiveDate,
+    tz: Tz,
+    week_start: Weekday,
+    highlight_today: bool,
+) -> String {
+    let first = month.with_day(1).unwrap();
+    let next = first + Months::new(1);
+    let mut occurrences: Vec<_> = calendar
+        .iter_range(zone::start_of_day(tz, first), zone::start_of_day(tz, next))
+        .collect();
+    occurrences.sort_by_key(|(schedule, _)| !schedule.is_all_day());
+
+    // 予定のある日
+    let mut marked = [false; 32];
+    for (schedule, occurrence) in &occurrences {
+        let (start, last) = covered_dates(schedule, occurrence, tz);
+        for date in start.iter_days().take_while(|date| *date <= last) {
+            if date.year() == first.year() && date.month() == first.month() {
+                marked[date.day() as usize] = true;
+            }
+        }
+    }
+
+    let mut output = String::new();
+    let title = format!("{}年{}月", first.year(), first.month());
+    let padding = (CELL_WIDTH * 7).saturating_sub(display_width(&title)) / 2;
+    writeln!(output, "{}{}", " ".repeat(padding), title).unwrap();
+    let header: Vec<&str> = iter::successors(Some(week_start), |weekday| Some(weekday.succ()))
+        .take(7)
+        .map(weekday_name)
+        .collect();
+    writeln!(output, "{}", header.join("  ")).unwrap();
+
+    let mut line = " ".repeat(CELL_WIDTH * first.weekday().days_since(week_start) as usize);
+    for date in first.iter_days().take_while(|date| *date < next) {
+        let day = if highlight_today && date == today {
+            format!("\x1b[7m{:>2}\x1b[0m", date.day())
+        } else {
+            format!("{:>2}", date.day())
+        };
+        let marker = if marked[date.day() as usize] {
+            '*'
+        } else {
+            ' '
+        };
+        write!(line, "{}{} ", day, marker).unwrap();
+        if (date + Days::new(1)).weekday() == week_start {
+            writeln!(output, "{}", line.trim_end()).unwrap();
+            line.clear();
+        }
+    }
+    if !line.is_empty() {
+        writeln!(output, "{}", line.trim_end()).unwrap();
+    }
+
+    writeln!(output).unwrap();
+    if occurrences.is_empty() {
+        writeln!(output, "予定はありません").unwrap();
+    }
+    for (schedule, occurrence) in &occurrences {
+        writeln!(
+            output,
+            "{}  {} (ID {})",
+            format_occurrence(schedule, occurrence, tz),
+            occurrence.subject,
+            schedule.id
+        )
+        .unwrap();
+    }
+    output
+}
+
+/// `date` を含む週を、`hours` の時間帯について1時間ごとの行で描画する
+///
+/// 予定は開始した行に `#ID タイトル`、続く行に帯を描く。同じ時間に重なって保存されている予定
+/// （手編集や取り込みで `intersects` の判定を経ずに入ったもの）は `!` を付けて示す。
+/// 終日の予定は時間の行の上の `終日` の行と、一覧の先頭に出す。
+pub fn render_week(
+    calendar: &Calendar,
+    date: NaiveDate,
+    tz: Tz,
+    week_start: Weekday,
+    hours: Range<u32>,
+) -> String {
+    let first = date - Days::new(date.weekday().days_since(week_start).into());
+    let days: Vec<NaiveDate> = first.iter_days().take(7).collect();
+    let (all_day, occurrences): (Vec<_>, Vec<_>) = calendar
+        .iter_range(
+            zone::start_of_day(tz, first),
+            zone::start_of_day(tz, first + Days::new(7)),
+        )
+        .partition(|(schedule, _)| schedule.is_all_day());
+
+    // 重なって保存されている発生の組
+    let mut overlaps = Vec::new();
+    for (i, (_, a)) in occurrences.iter().enumerate() {
+        for (j, (_, b)) in occurrences.iter().enumerate().skip(i + 1) {
+            if a.start < b.end && b.start < a.end {
+                overlaps.push((i, j));
+            }
+        }
+    }
+    let overlapping = |i: usize, j: usize| overlaps.contains(&(i.min(j), i.max(j)));
+
+    let mut output = String::new();
+    let header: Vec<String> = days
+        .iter()
+        .map(|day| {
+            let label = format!(
+                "{}/{}({})",
+                day.month(),
+                day.day(),
+                weekday_name(day.weekday())
+            );
+            fit_width(&label, COLUMN_WIDTH)
+        })
+        .collect();
+    writeln!(output, "      {}", header.join("|").trim_end()).unwrap();
+
+    if !all_day.is_empty() {
+        let cells: Vec<String> = days
+            .iter()
+            .map(|day| {
+                let on_day: Vec<_> = all_day
+                    .iter()
+                    .filter(|(_, occurrence)| {
+                        let (first, last) = all_day_dates(occurrence);
+                        first <= *day && *day <= last
+                    })
+                    .collect();
+                let text = match on_day.as_slice() {
+                    [] => String::new(),
+                    [(schedule, occurrence)] => format!("#{} {}", schedule.id, occurrence.subject),
+                    many => {
+                        let ids: Vec<String> = many
+                            .iter()
+                            .map(|(schedule, _)| format!("#{}", schedule.id))
+                            .collect();
+                        ids.join(",")
+                    }
+                };
+                fit_width(&text, COLUMN_WIDTH)
+            })
+            .collect();
+        writeln!(output, "終日  {}", cells.join("|").trim_end()).unwrap();
+    }
+
+    let first_hour = hours.start;
+    for hour in hours {
+        let mut cells = Vec::new();
+        for day in &days {
+            let row_start = day.and_time(NaiveTime::from_hms_opt(hour, 0, 0).unwrap());
+            let row_end = row_start + Duration::hours(1);
+            let in_row: Vec<usize> = occurrences
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, occurrence))| {
+                    let (start, end) = local_span(occurrence, tz);
+                    start < row_end && (end > row_start || (start == end && start >= row_start))
+                })
+                .map(|(index, _)| index)
+                .collect();
+            let text = match in_row.as_slice() {
+                [] => String::new(),
+                [index] => {
+                    let (schedule, occurrence) = &occurrences[*index];
+                    let (start, _) = local_span(occurrence, tz);
+                    // 開始した行か、表示範囲より前に始まった予定なら最初の行に見出しを出す
+                    if start >= row_start || hour == first_hour {
+                        format!("#{} {}", schedule.id, occurrence.subject)
+                    } else {
+                        "█".repeat(COLUMN_WIDTH)
+                    }
+                }
+                indexes => {
+                    let conflict = indexes
+                        .iter()
+                        .any(|&i| indexes.iter().any(|&j| i != j && overlapping(i, j)));
+                    let ids: Vec<String> = indexes
+                        .iter()
+                        .map(|&i| format!("#{}", occurrences[i].0.id))
+                        .collect();
+                    format!("{}{}", if conflict { "!" } else { "" }, ids.join(","))
+                }
+            };
+            cells.push(fit_width(&text, COLUMN_WIDTH));
+        }
+        writeln!(output, "{:02}:00 {}", hour, cells.join("|").trim_end()).unwrap();
+    }
+
+    writeln!(output).unwrap();
+    if all_day.is_empty() && occurrences.is_empty() {
+        writeln!(output, "予定はありません").unwrap();
+    }
+    for (schedule, occurrence) in &all_day {
+        writeln!(
+            output,
+            "{}  {} (ID {})",
+            format_occurrence(schedule, occurrence, tz),
+            occurrence.subject,
+            schedule.id
+        )
+        .unwrap();
+    }
+    for (index, (schedule, occurrence)) in occurrences.iter().enumerate() {
+        let (start, end) = local_span(occurrence, tz);
+        write!(
+            output,
+            "{}  {} (ID {})",
+            format_span(start, end),
+            occurrence.subject,
+            schedule.id
+        )
+        .unwrap();
+        let conflicts: Vec<String> = (0..occurrences.len())
+            .filter(|&other| other != index && overlapping(index, other))
+            .map(|other| format!("ID {}", occurrences[other].0.id))
+            .collect();
+        if !conflicts.is_empty() {
+            write!(output, "  ! {} と重複", conflicts.join(", ")).unwrap();
+        }
+        writeln!(output).unwrap();
+    }
+    output
+}
+
+/// `1日2時間3分` 形式の長さ（1分未満は切り捨てる）
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    let parts = [
+        (minutes / (24 * 60), "日"),
+        (minutes / 60 % 24, "時間"),
+        (minutes % 60, "分"),
+    ];
+    let text: String = parts
+        .iter()
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect();
+    if text.is_empty() {
+        "1分未満".to_string()
+    } else {
+        text
+    }
+}
+
+/// 今日から `offset` 日後を初日として、`days` 日分の予定を日ごとに描画する
+///
+/// 各日の終日の予定は時刻のある予定より先に出す。実行中の予定には `▶` と残り時間を、
+/// 次に始まる予定には開始までの時間を付ける。
+pub fn render_agenda(
+    calendar: &Calendar,
+    clock: &impl Clock,
+    offset: u64,
+    days: u64,
+    tz: Tz,
+) -> String {
+    let now = clock.now();
+    let today = now.with_timezone(&tz).date_naive();
+    let first = today + Days::new(offset);
+    let last = first + Days::new(days);
+    let (all_day, occurrences): (Vec<_>, Vec<_>) = calendar
+        .iter_range(zone::start_of_day(tz, first), zone::start_of_day(tz, last))
+        .partition(|(schedule, _)| schedule.is_all_day());
+    let next_start: Option<DateTime<Tz>> = occurrences
+        .iter()
+        .map(|(_, occurrence)| occurrence.start)
+        .find(|start| *start > now);
+
+    let mut output = String::new();
+    for date in first.iter_days().take_while(|date| *date < last) {
+        let label = match (date - today).num_days() {
+            0 => " 今日",
+            1 => " 明日",
+            _ => "",
+        };
+        writeln!(
+            output,
+            "{}({}){}",
+            date.format("%Y-%m-%d"),
+            weekday_name(date.weekday()),
+            label
+        )
+        .unwrap();
+
+        let mut empty = true;
+        for (schedule, occurrence) in &all_day {
+            let (first_date, last_date) = all_day_dates(occurrence);
+            if date < first_date || last_date < date {
+                continue;
+            }
+            empty = false;
+            // 複数日にわたる場合は期間を添える
+            let dates = if first_date == last_date {
+                String::new()
+            } else {
+                format!(" {}", format_dates(first_date, last_date))
+            };
+            writeln!(
+                output,
+                "  終日{}  {} (ID {})",
+                dates, occurrence.subject, schedule.id
+            )
+            .unwrap();
+        }
+        for (schedule, occurrence) in &occurrences {
+            let (start, end) = local_span(occurrence, tz);
+            let (first_date, last_date) = covered_dates(schedule, occurrence, tz);
+            if date < first_date || last_date < date {
+                continue;
+            }
+            empty = false;
+            let span = if start.date() == end.date() {
+                format!("{}-{}", start.format("%H:%M"), end.format("%H:%M"))
+            } else {
+                format_span(start, end)
+            };
+            let running = occurrence.start <= now && now < occurrence.end;
+            let note = if running {
+                format!(
+                    "（実行中、残り{}）",
+                    format_duration(occurrence.end.signed_duration_since(now))
+                )
+            } else if Some(occurrence.start) == next_start {
+                format!(
+                    "（あと{}）",
+                    format_duration(occurrence.start.signed_duration_since(now))
+                )
+            } else {
+                String::new()
+            };
+            writeln!(
+                output,
+                "{} {}  {} (ID {}){}",
+                if running { "▶" } else { " " },
+                span,
+                occurrence.subject,
+                schedule.id,
+                note
+            )
+            .unwrap();
+        }
+        if empty {
+            writeln!(output, "  予定はありません").unwrap();
+        }
+    }
+    output
+}
+
+/// 予定の詳細を `tz` での日時で描画する（設定されていない項目は出さない）
+pub fn render_detail(schedule: &Schedule, tz: Tz) -> String {
+    let mut output = String::new();
+    writeln!(output, "ID: {}", schedule.id).unwrap();
+    writeln!(output, "タイトル: {}", schedule.subject).unwrap();
+    match schedule.all_day {
+        Some(availability) => {
+            let note = match availability {
+                Availability::Busy => "予定あり",
+                Availability::Free => "空き",
+            };
+            let span = format_schedule(schedule, tz);
+            writeln!(output, "日時: {}（{}）", span, note).unwrap();
+        }
+        None => {
+            let span = format_schedule(schedule, tz);
+            writeln!(output, "日時: {} ({})", span, tz.name()).unwrap();
+        }
+    }
+    if let Some(rule) = &schedule.recurrence {
+        writeln!(output, "繰り返し: {}", rule).unwrap();
+    }
+    if let Some(location) = &schedule.location {
+        writeln!(output, "場所: {}", location).unwrap();
+    }
+    if !schedule.tags.is_empty() {
+        writeln!(output, "タグ: {}", schedule.tags.join(", ")).unwrap();
+    }
+    if !schedule.attendees.is_empty() {
+        writeln!(output, "参加者:").unwrap();
+        for attendee in &schedule.attendees {
+            writeln!(output, "  {}  {}", attendee, attendee.status).unwrap();
+        }
+    }
+    if let Some(description) = &schedule.description {
+        writeln!(output, "説明:").unwrap();
+        for line in description.lines() {
+            writeln!(output, "  {}", line).unwrap();
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        attendee::{Attendee, Rsvp},
+        clock::FixedClock,
+        test_util::native_date_time_from_str,
+    };
+    use rstest::rstest;
+
+    fn calendar() -> Calendar {
+        Calendar::new(
+            vec![
+                Schedule::new(
+                    0,
+                    "出張".to_string(),
+                    native_date_time_from_str("2024-01-30T09:00:00"),
+                    native_date_time_from_str("2024-02-01T18:00:00"),
+                ),
+                Schedule {
+                    recurrence: Some("FREQ=WEEKLY;BYDAY=MO".parse().unwrap()),
+                    ..Schedule::new(
+                        1,
+                        "定例".to_string(),
+                        native_date_time_from_str("2024-02-05T09:00:00"),
+                        native_date_time_from_str("2024-02-05T10:00:00"),
+                    )
+                },
+            ],
+            2,
+        )
+    }
+
+    #[rstest]
+    #[case(
+        Weekday::Mon,
+        "月  火  水  木  金  土  日\n             1*  2   3   4\n 5*  6   7   8   9  10  11\n"
+    )]
+    #[case(
+        Weekday::Sun,
+        "日  月  火  水  木  金  土\n                 1*  2   3\n 4   5*  6   7   8   9  10\n"
+    )]
+    fn test_render_month(#[case] week_start: Weekday, #[case] expected_head: &str) {
+        let output = render_month(
+            &calendar(),
+            NaiveDate::from_ymd_opt(2024, 2, 15).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(),
+            Tz::UTC,
+            week_start,
+            false,
+        );
+        let mut lines = output.lines();
+        assert_eq!(lines.next(), Some("         2024年2月"));
+        assert!(output.contains(expected_head), "{}", output);
+        assert!(output.contains("26*"));
+        assert!(output.contains("\n1/30(火) 09:00-2/1 18:00  出張 (ID 0)\n"));
+        assert!(output.contains("\n2/26(月) 09:00-10:00  定例 (ID 1)\n"));
+        assert!(!output.contains("3/4(月)"));
+    }
+
+    #[test]
+    fn test_render_month_highlights_today() {
+        let output = render_month(
+            &calendar(),
+            NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 2, 7).unwrap(),
+            Tz::UTC,
+            Weekday::Mon,
+            true,
+        );
+        assert!(output.contains("\x1b[7m 7\x1b[0m "));
+    }
+
+    #[test]
+    fn test_render_week() {
+        const EMPTY_DAYS: &str =
+            "|            |            |            |            |            |";
+        let mut calendar = calendar();
+        // intersects の判定を経ずに保存された重複
+        calendar.insert(Schedule::new(
+            2,
+            "来客".to_string(),
+            native_date_time_from_str("2024-02-05T09:30:00"),
+            native_date_time_from_str("2024-02-05T11:00:00"),
+        ));
+        let output = render_week(
+            &calendar,
+            NaiveDate::from_ymd_opt(2024, 2, 7).unwrap(),
+            Tz::UTC,
+            Weekday::Mon,
+            9..12,
+        );
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines[0],
+            "      2/5(月)     |2/6(火)     |2/7(水)     |2/8(木)     |2/9(金)     |2/10(土)    |2/11(日)"
+        );
+        assert_eq!(lines[1], format!("09:00 !#1,#2      {}", EMPTY_DAYS));
+        assert_eq!(lines[2], format!("10:00 ████████████{}", EMPTY_DAYS));
+        assert_eq!(lines[3], format!("11:00             {}", EMPTY_DAYS));
+        assert!(output.contains("\n2/5(月) 09:00-10:00  定例 (ID 1)  ! ID 2 と重複\n"));
+        assert!(output.contains("\n2/5(月) 09:30-11:00  来客 (ID 2)  ! ID 1 と重複\n"));
+    }
+
+    #[test]
+    fn test_render_all_day() {
+        let mut calendar = calendar();
+        // 東京で登録した終日の予定も、UTC で表示して同じ日付に出す
+        calendar.insert(Schedule {
+            tz: Some(chrono_tz::Asia::Tokyo),
+            ..Schedule::new_all_day(
+                2,
+                "休暇".to_string(),
+                NaiveDate::from_ymd_opt(2024, 2, 6).unwrap(),
+                NaiveDate::from_ymd_opt(2024, 2, 7).unwrap(),
+                Availability::Busy,
+            )
+        });
+        calendar.insert(Schedule::new_all_day(
+            3,
+            "締め切り".to_string(),
+            NaiveDate::from_ymd_opt(2024, 2, 5).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 2, 5).unwrap(),
+            Availability::Free,
+        ));
+
+        let week = render_week(
+            &calendar,
+            NaiveDate::from_ymd_opt(2024, 2, 5).unwrap(),
+            Tz::UTC,
+            Weekday::Mon,
+            9..10,
+        );
+        let lines: Vec<&str> = week.lines().collect();
+        assert_eq!(
+            lines[1],
+            "終日  #3 締め切り |#2 休暇     |#2 休暇     |            |            |            |"
+        );
+        assert!(week.contains("\n\n2/5(月) 終日  締め切り (ID 3)\n2/6(火)-2/7(水) 終日  休暇 (ID 2)\n2/5(月) 09:00-10:00  定例 (ID 1)\n"));
+
+        let clock = FixedClock(native_date_time_from_str("2024-02-05T08:00:00").and_utc());
+        assert_eq!(
+            render_agenda(&calendar, &clock, 0, 2, Tz::UTC),
+            "\
+2024-02-05(月) 今日
+  終日  締め切り (ID 3)
+  09:00-10:00  定例 (ID 1)（あと1時間）
+2024-02-06(火) 明日
+  終日 2/6(火)-2/7(水)  休暇 (ID 2)
+"
+        );
+
+        let month = render_month(
+            &calendar,
+            NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(),
+            Tz::UTC,
+            Weekday::Mon,
+            false,
+        );
+        assert!(month.contains("\n\n2/5(月) 終日  締め切り (ID 3)\n2/6(火)-2/7(水) 終日  休暇 (ID 2)\n1/30(火) 09:00-2/1 18:00  出張 (ID 0)\n"));
+        assert!(render_detail(calendar.find(2).unwrap(), Tz::UTC)
+            .contains("\n日時: 2/6(火)-2/7(水) 終日（予定あり）\n"));
+    }
+
+    #[test]
+    fn test_render_agenda() {
+        let mut calendar = calendar();
+        calendar.insert(Schedule::new(
+            2,
+            "来客".to_string(),
+            native_date_time_from_str("2024-02-05T13:00:00"),
+            native_date_time_from_str("2024-02-05T14:00:00"),
+        ));
+        calendar.insert(Schedule::new(
+            3,
+            "出張".to_string(),
+            native_date_time_from_str("2024-02-06T18:00:00"),
+            native_date_time_from_str("2024-02-07T12:00:00"),
+        ));
+        let clock = FixedClock(native_date_time_from_str("2024-02-05T09:15:00").and_utc());
+        assert_eq!(
+            render_agenda(&calendar, &clock, 0, 3, Tz::UTC),
+            "\
+2024-02-05(月) 今日
+▶ 09:00-10:00  定例 (ID 1)（実行中、残り45分）
+  13:00-14:00  来客 (ID 2)（あと3時間45分）
+2024-02-06(火) 明日
+  2/6(火) 18:00-2/7 12:00  出張 (ID 3)
+2024-02-07(水)
+  2/6(火) 18:00-2/7 12:00  出張 (ID 3)
+"
+        );
+        assert_eq!(
+            render_agenda(&calendar, &clock, 4, 1, Tz::UTC),
+            "2024-02-09(金)\n  予定はありません\n"
+        );
+    }
+
+    #[test]
+    fn test_render_detail() {
+        let schedule = Schedule {
+            tz: Some(chrono_tz::Asia::Tokyo),
+            location: Some("会議室A".to_string()),
+            description: Some("議題:\n- 予算".to_string()),
+            attendees: vec![
+                Attendee {
+                    name: "山田太郎".to_string(),
+                    email: "taro@example.com".to_string(),
+                    status: Rsvp::Accepted,
+                },
+                Attendee {
+                    name: String::new(),
+                    email: "hanako@example.com".to_string(),
+                    status: Rsvp::NeedsAction,
+                },
+            ],
+            ..Schedule::new(
+                3,
+                "予算会議".to_string(),
+                native_date_time_from_str("2024-02-05T09:00:00"),
+                native_date_time_from_str("2024-02-05T10:00:00"),
+            )
+        };
+        assert_eq!(
+            render_detail(&schedule, Tz::UTC),
+            "\
+ID: 3
+タイトル: 予算会議
+日時: 2/5(月) 00:00-01:00 (UTC)
+場所: 会議室A
+参加者:
+  山田太郎 <taro@example.com>  出席
+  <hanako@example.com>  未回答
+説明:
+  議題:
+  - 予算
+"
+        );
+
+        // 設定されていない項目は出さない
+        assert_eq!(
+            render_detail(&calendar().schedules()[0], Tz::UTC),
+            "ID: 0\nタイトル: 出張\n日時: 1/30(火) 09:00-2/1 18:00 (UTC)\n"
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::env;
+
+/// 既定のタイムゾーンを IANA 名で指定する環境変数
+pub const DEFAULT_TZ_ENV: &str = "CALENDAR_TZ";
+
+/// タイムゾーンの指定がない予定や日時に使うタイムゾーン
+///
+/// `CALENDAR_TZ`、`TZ` の順に IANA 名として解釈できるものを使い、どちらもなければ UTC とする。
+pub fn default_tz() -> Tz {
+    [DEFAULT_TZ_ENV, "TZ"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find_map(|value| value.trim_start_matches(':').parse().ok())
+        .unwrap_or(Tz::UTC)
+}
+
+/// `tz` での壁時計の日時を絶対時刻に解決する
+///
+/// 夏時間の終了で2回現れる時刻は早い方、夏時間の開始で存在しない時刻は
+/// 切り替え前のオフセットで解釈する（RFC 5545 と同じ扱い）。
+pub fn resolve_local(tz: Tz, local: NaiveDateTime) -> DateTime<Tz> {
+    tz.from_local_datetime(&local)
+        .earliest()
+        .unwrap_or_else(|| {
+            let offset = tz
+                .offset_from_utc_datetime(&(local - Duration::days(1)))
+                .fix();
+            tz.from_utc_datetime(&(local - offset))
+        })
+}
+
+/// `from` での壁時計の日時を `to` での壁時計の日時に変換する
+pub fn convert(local: NaiveDateTime, from: Tz, to: Tz) -> NaiveDateTime {
+    resolve_local(from, local).with_timezone(&to).naive_local()
+}
+
+/// `tz` での日付 `date` の始まり（0時）の絶対時刻
+pub fn start_of_day(tz: Tz, date: NaiveDate) -> DateTime<Utc> {
+    resolve_local(tz, date.and_time(Default::default())).with_timezone(&Utc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::native_date_time_from_str;
+    use rstest::rstest;
+
+    #[rstest]
+    // 通常の時刻
+    #[case("2024-07-01T09:00:00", "2024-07-01T07:00:00")]
+    // 夏時間の開始で存在しない 2:30 は切り替え後の 3:30 (CEST)
+    #[case("2024-03-31T02:30:00", "2024-03-31T01:30:00")]
+    // 夏時間の終了で2回ある 2:30 は早い方 (CEST)
+    #[case("2024-10-27T02:30:00", "2024-10-27T00:30:00")]
+    fn test_resolve_local(#[case] local: &str, #[case] utc: &str) {
+        let resolved = resolve_local(chrono_tz::Europe::Berlin, native_date_time_from_str(local));
+        assert_eq!(resolved.naive_utc(), native_date_time_from_str(utc));
+    }
+
+    #[test]
+    fn test_convert() {
+        assert_eq!(
+            convert(
+                native_date_time_from_str("2024-01-01T09:00:00"),
+                chrono_tz::Asia::Tokyo,
+                chrono_tz::Europe::Berlin
+            ),
+            native_date_time_from_str("2024-01-01T01:00:00")
+        );
+    }
+}